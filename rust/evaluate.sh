@@ -0,0 +1,14 @@
+#!/bin/bash
+
+SCENARIO_NAME=$1
+
+if [ -z "$SCENARIO_NAME" ] ; then
+  echo "usage: ./evaluate.sh <SCENARIO_NAME> [<JSON_OUTPUT_FILE>]"
+  exit 1
+fi
+
+if [ -z "$2" ] ; then
+  RUST_BACKTRACE=1 RUST_LOG=info cargo run --bin evaluate -- --scenario_name=$SCENARIO_NAME
+else
+  RUST_BACKTRACE=1 RUST_LOG=info cargo run --bin evaluate -- --scenario_name=$SCENARIO_NAME --evaluation_output_file=$2
+fi
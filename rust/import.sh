@@ -0,0 +1,12 @@
+#!/bin/bash
+
+SCENARIO_NAME=$1
+ENTITIES_FILE=$2
+FACTS_FILE=$3
+
+if [ -z "$SCENARIO_NAME" ] || [ -z "$ENTITIES_FILE" ] || [ -z "$FACTS_FILE" ] ; then
+  echo "usage: ./import.sh <SCENARIO_NAME> <ENTITIES_FILE> <FACTS_FILE>"
+  exit 1
+fi
+
+RUST_BACKTRACE=1 RUST_LOG=info cargo run --bin import -- --scenario_name=$SCENARIO_NAME --entities_file=$ENTITIES_FILE --facts_file=$FACTS_FILE
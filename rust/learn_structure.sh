@@ -0,0 +1,10 @@
+#!/bin/bash
+
+SCENARIO_NAME=$1
+
+if [ -z "$SCENARIO_NAME" ] ; then
+  echo "usage: ./learn_structure.sh <SCENARIO_NAME>"
+  exit 1
+fi
+
+RUST_BACKTRACE=1 RUST_LOG=info cargo run --bin learn_structure -- --scenario_name=$SCENARIO_NAME
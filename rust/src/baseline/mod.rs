@@ -1 +1 @@
-mod model;
+pub mod model;
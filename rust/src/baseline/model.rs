@@ -1,13 +1,269 @@
-use std::{collections::HashMap, error::Error};
-use crate::model::objects::PredicateGroup;
+use std::{error::Error, sync::Arc};
 
+use redis::Connection;
+
+use crate::{
+    common::{
+        checkpoint::checkpoint_namespace,
+        graph::InferenceGraph,
+        interface::{BeliefTable, PredictStatistics, TrainStatistics},
+        model::{FactorContext, FactorModel},
+        premise::PremiseEstimator,
+        proposition_db::RedisBeliefTable,
+        redis::{is_member, key_delete, map_delete, map_get, map_get_all, map_insert, set_add},
+        train::{extract_factor_for_proposition_for_training, TrainingPlan},
+    },
+    model::objects::ImplicationFactor,
+};
+
+/// Added to both the true and the false count of each row of a conditional probability table.
+const PSEUDO_COUNT: f64 = 1f64;
+/// Each row is a 0/1 assignment to the premises, so the table grows as 2^n.
+const MAX_ENUMERATED_PREMISES: usize = 16;
+
+/// A naive Bayesian network baseline: the conditional probability of each conclusion given each
+/// 0/1 assignment to its premises, estimated by counting over the training data. Unlike the
+/// exponential model, nothing is shared between rows.
 pub struct MonolithicBayes {
-    underlying:HashMap<PredicateGroup, f64>,
+    namespace: String,
 }
 
-
 impl MonolithicBayes {
-    pub fn new() -> Result<Self, Box<dyn Error>> {
-        Ok(MonolithicBayes{ underlying: HashMap::new() })
+    pub const COUNTS_KEY: &'static str = "cpt_counts";
+    pub const IMPLICATIONS_KEY: &'static str = "cpt_implications";
+    /// The checkpoint that `fit` counts into, so that the live model is left alone. It starts
+    /// with `RESERVED_CHECKPOINT_PREFIX`.
+    pub const FIT_CHECKPOINT: &'static str = "__baseline";
+
+    pub fn new(namespace: String) -> Result<Self, Box<dyn Error>> {
+        Ok(MonolithicBayes { namespace })
+    }
+
+    pub fn new_mutable(namespace: String) -> Result<Box<dyn FactorModel>, Box<dyn Error>> {
+        Ok(Box::new(MonolithicBayes::new(namespace)?))
+    }
+
+    pub fn new_shared(namespace: String) -> Result<Arc<dyn FactorModel>, Box<dyn Error>> {
+        Ok(Arc::new(MonolithicBayes::new(namespace)?))
+    }
+
+    /// Counts over the training queue of `namespace` from scratch, for comparison with the
+    /// model that the namespace was trained with. The counts go to the `FIT_CHECKPOINT` of
+    /// `namespace`, which `clear_fit` deletes.
+    pub fn fit(
+        connection: &mut Connection,
+        namespace: String,
+    ) -> Result<Arc<dyn FactorModel>, Box<dyn Error>> {
+        Self::clear_fit(connection, &namespace)?;
+        let fit_namespace = Self::fit_namespace(&namespace);
+        let mut baseline = MonolithicBayes::new(fit_namespace.clone())?;
+        let graph = InferenceGraph::new_mutable(namespace.clone())?;
+        let proposition_db = RedisBeliefTable::new_mutable(connection, namespace.clone())?;
+        let plan = TrainingPlan::new(namespace.clone())?;
+        for implication in graph.get_all_implications(connection)? {
+            set_add(
+                connection,
+                &fit_namespace,
+                Self::IMPLICATIONS_KEY,
+                &implication.unique_key(),
+            )?;
+        }
+        let estimator = PremiseEstimator::Product;
+        for example in plan.get_training_examples(connection)? {
+            let proposition = example.proposition;
+            let gold = match proposition_db.get_proposition_probability(connection, &proposition)? {
+                Some(gold) => gold,
+                None => continue,
+            };
+            let factor = extract_factor_for_proposition_for_training(
+                connection,
+                &proposition_db,
+                &estimator,
+                &graph,
+                proposition,
+            )?;
+            if let Some(factor) = factor {
+                baseline.train(connection, &factor, gold, example.weight)?;
+            }
+        }
+        Ok(Arc::new(baseline))
+    }
+
+    fn fit_namespace(namespace: &str) -> String {
+        checkpoint_namespace(namespace, &Some(Self::FIT_CHECKPOINT.to_string()))
+    }
+
+    /// Deletes the counts that `fit` made for `namespace`.
+    pub fn clear_fit(connection: &mut Connection, namespace: &str) -> Result<(), Box<dyn Error>> {
+        let fit_namespace = Self::fit_namespace(namespace);
+        key_delete(connection, &fit_namespace, Self::COUNTS_KEY)?;
+        key_delete(connection, &fit_namespace, Self::IMPLICATIONS_KEY)?;
+        Ok(())
     }
-}
\ No newline at end of file
+
+    fn read_count(&self, connection: &mut Connection, field: &str) -> Result<f64, Box<dyn Error>> {
+        match map_get(connection, &self.namespace, Self::COUNTS_KEY, field)? {
+            Some(record) => Ok(record.parse::<f64>()?),
+            None => Ok(0f64),
+        }
+    }
+
+    fn add_count(
+        &self,
+        connection: &mut Connection,
+        field: &str,
+        amount: f64,
+    ) -> Result<(), Box<dyn Error>> {
+        let count = self.read_count(connection, field)? + amount;
+        map_insert(
+            connection,
+            &self.namespace,
+            Self::COUNTS_KEY,
+            field,
+            &count.to_string(),
+        )
+    }
+
+    fn row_probability(
+        &self,
+        connection: &mut Connection,
+        row: &str,
+    ) -> Result<f64, Box<dyn Error>> {
+        let true_count = self.read_count(connection, &format!("{} true", row))?;
+        let total_count = self.read_count(connection, &format!("{} total", row))?;
+        Ok((true_count + PSEUDO_COUNT) / (total_count + 2f64 * PSEUDO_COUNT))
+    }
+}
+
+/// Every 0/1 assignment to the premises of `factor`, with its probability when the premises are
+/// independent with the probabilities in `factor`.
+fn weighted_assignments(factor: &FactorContext) -> Result<Vec<(Vec<bool>, f64)>, Box<dyn Error>> {
+    let size = factor.factor.len();
+    if size > MAX_ENUMERATED_PREMISES {
+        return Err(format!(
+            "Too many premises for a conditional probability table: {}",
+            size
+        )
+        .into());
+    }
+    let mut result = vec![];
+    for index in 0..(1usize << size) {
+        let mut assignment = vec![];
+        let mut weight = 1f64;
+        for (position, probability) in factor.probabilities.iter().enumerate() {
+            let value = (index >> position) & 1 == 1;
+            weight *= if value {
+                *probability
+            } else {
+                1f64 - probability
+            };
+            assignment.push(value);
+        }
+        if weight > 0f64 {
+            result.push((assignment, weight));
+        }
+    }
+    Ok(result)
+}
+
+/// Names the row of the table for `assignment`, by the conclusion relation and the sorted
+/// implication keys, so that the row does not depend on the order of the backimplications.
+fn row_key(factor: &FactorContext, assignment: &[bool]) -> String {
+    let relation = match factor.factor.first() {
+        Some(premise) => premise.conclusion.predicate.relation.relation_name.clone(),
+        None => "".to_string(),
+    };
+    let mut parts: Vec<String> = factor
+        .factor
+        .iter()
+        .zip(assignment)
+        .map(|(premise, value)| format!("{}={}", premise.inference.unique_key(), *value as u8))
+        .collect();
+    parts.sort();
+    format!("{}|{}", relation, parts.join("|"))
+}
+
+/// Whether the count `field`, a row key plus " true" or " total", is for a row that assigns a
+/// value to the implication with `implication_key`.
+fn row_uses_implication(field: &str, implication_key: &str) -> bool {
+    let row = match field.rsplit_once(' ') {
+        Some((row, _)) => row,
+        None => return false,
+    };
+    row.split('|')
+        .skip(1)
+        .filter_map(|part| part.rsplit_once('='))
+        .any(|(key, _)| key == implication_key)
+}
+
+impl FactorModel for MonolithicBayes {
+    /// The counts of the rows that assign a value to `implication` start over. Rows of other
+    /// implications are kept.
+    fn initialize_connection(
+        &mut self,
+        connection: &mut Connection,
+        implication: &ImplicationFactor,
+    ) -> Result<(), Box<dyn Error>> {
+        let implication_key = implication.unique_key();
+        for field in map_get_all(connection, &self.namespace, Self::COUNTS_KEY)?.keys() {
+            if row_uses_implication(field, &implication_key) {
+                map_delete(connection, &self.namespace, Self::COUNTS_KEY, field)?;
+            }
+        }
+        set_add(
+            connection,
+            &self.namespace,
+            Self::IMPLICATIONS_KEY,
+            &implication.unique_key(),
+        )?;
+        Ok(())
+    }
+
+    fn is_connection_initialized(
+        &self,
+        connection: &mut Connection,
+        implication: &ImplicationFactor,
+    ) -> Result<bool, Box<dyn Error>> {
+        is_member(
+            connection,
+            &self.namespace,
+            Self::IMPLICATIONS_KEY,
+            &implication.unique_key(),
+        )
+    }
+
+    fn train(
+        &mut self,
+        connection: &mut Connection,
+        factor: &FactorContext,
+        gold_probability: f64,
+        example_weight: f64,
+    ) -> Result<TrainStatistics, Box<dyn Error>> {
+        let predicted = self.predict(connection, factor)?.probability;
+        for (assignment, weight) in weighted_assignments(factor)? {
+            let row = row_key(factor, &assignment);
+            let weight = weight * example_weight;
+            self.add_count(
+                connection,
+                &format!("{} true", row),
+                weight * gold_probability,
+            )?;
+            self.add_count(connection, &format!("{} total", row), weight)?;
+        }
+        Ok(TrainStatistics::new(predicted, gold_probability))
+    }
+
+    fn predict(
+        &self,
+        connection: &mut Connection,
+        factor: &FactorContext,
+    ) -> Result<PredictStatistics, Box<dyn Error>> {
+        let mut probability = 0f64;
+        for (assignment, weight) in weighted_assignments(factor)? {
+            let row = row_key(factor, &assignment);
+            probability += weight * self.row_probability(connection, &row)?;
+        }
+        trace!("monolithic predict: {:?} {}", factor, probability);
+        Ok(PredictStatistics { probability })
+    }
+}
@@ -0,0 +1,24 @@
+use bayes_star::common::checkpoint::{get_checkpoints, restore_checkpoint, save_checkpoint};
+use bayes_star::common::resources::ResourceContext;
+use bayes_star::common::setup::parse_configuration_options;
+
+extern crate log;
+
+fn main() {
+    let config = parse_configuration_options();
+    let resources = ResourceContext::new(&config).expect("Couldn't create resources.");
+    let mut connection = resources.connection.lock().unwrap();
+    let namespace = &config.scenario_name;
+    if let Some(name) = &config.save_checkpoint {
+        save_checkpoint(&mut connection, namespace, name).expect("Couldn't save checkpoint.");
+        println!("saved checkpoint {}", name);
+    }
+    if let Some(name) = &config.restore_checkpoint {
+        restore_checkpoint(&mut connection, namespace, name).expect("Couldn't restore checkpoint.");
+        println!("restored checkpoint {}", name);
+    }
+    for name in get_checkpoints(&mut connection, namespace).expect("Couldn't list checkpoints.") {
+        println!("checkpoint {}", name);
+    }
+    println!("main finishes");
+}
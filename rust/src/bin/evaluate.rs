@@ -0,0 +1,39 @@
+use std::fs;
+
+use bayes_star::common::evaluate::{run_baseline_comparison, run_evaluation};
+use bayes_star::common::resources::ResourceContext;
+use bayes_star::common::setup::parse_configuration_options;
+
+extern crate log;
+
+fn main() {
+    let config = parse_configuration_options();
+    let resources = ResourceContext::new(&config).expect("Couldn't create resources.");
+    let mut connection = resources.connection.lock().unwrap();
+    let json = if config.compare_baseline {
+        let comparison = run_baseline_comparison(
+            &mut connection,
+            &config.scenario_name,
+            &config.checkpoint,
+            &resources.convergence,
+        )
+        .expect("Evaluation failed.");
+        println!("{}", comparison.render_text());
+        serde_json::to_string_pretty(&comparison).expect("Couldn't serialize report.")
+    } else {
+        let report = run_evaluation(
+            &mut connection,
+            &config.scenario_name,
+            &config.checkpoint,
+            &resources.convergence,
+        )
+        .expect("Evaluation failed.");
+        println!("{}", report.render_text());
+        serde_json::to_string_pretty(&report).expect("Couldn't serialize report.")
+    };
+    match &config.evaluation_output_file {
+        Some(file_name) => fs::write(file_name, json).expect("Couldn't write report."),
+        None => println!("{}", json),
+    }
+    println!("main finishes");
+}
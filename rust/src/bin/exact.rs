@@ -0,0 +1,32 @@
+use bayes_star::common::resources::ResourceContext;
+use bayes_star::common::setup::parse_configuration_options;
+use bayes_star::inference::rounds::compare_exact_inference;
+
+extern crate log;
+
+fn main() {
+    let config = parse_configuration_options();
+    let resources = ResourceContext::new(&config).expect("Couldn't create resources.");
+    let test_scenario = config.test_scenario.expect("no test_scenario in config");
+    let mut connection = resources.connection.lock().unwrap();
+    let (exact, comparison, report) = compare_exact_inference(
+        &mut connection,
+        &config.scenario_name,
+        &test_scenario,
+        &config.checkpoint,
+        &resources.convergence,
+    )
+    .expect("Exact inference failed.");
+    for (node, probability) in exact.entries() {
+        println!("exact {:.8} {}", probability, node);
+    }
+    println!(
+        "belief propagation vs exact over {} nodes: max absolute error {:.8}, mean absolute error {:.8}, worst {:?}",
+        comparison.num_nodes,
+        comparison.max_absolute_error,
+        comparison.mean_absolute_error,
+        comparison.worst_node
+    );
+    println!("belief propagation convergence {:?}", report);
+    println!("main finishes");
+}
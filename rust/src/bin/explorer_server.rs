@@ -7,7 +7,7 @@ use bayes_star::{
         resources::ResourceContext,
         setup::{parse_configuration_options, CommandLineOptions},
     },
-    explorer::routes::{animation_route::internal_animation, experiment_route::internal_experiment, factors_route::internal_factors, index_route::internal_index, marginals_route::internal_marginals, network_route::internal_network, weights_route::internal_weights},
+    explorer::routes::{animation_route::internal_animation, experiment_route::internal_experiment, factors_route::internal_factors, index_route::internal_index, marginals_route::internal_marginals, network_route::internal_network, training_route::internal_training, weights_route::internal_weights},
 };
 use rocket::response::content::Html;
 use rocket::State;
@@ -44,6 +44,11 @@ fn weights(experiment_name: String, context: State<WebContext>) -> Html<String>
     internal_weights(&experiment_name, &context.namespace)
 }
 
+#[get("/training/<experiment_name>")]
+fn training(experiment_name: String, context: State<WebContext>) -> Html<String> {
+    internal_training(&experiment_name, &context.namespace)
+}
+
 #[get("/marginals/<experiment_name>/<test_scenario>")]
 fn marginals(experiment_name: String, test_scenario: String, context: State<WebContext>) -> Html<String> {
     internal_marginals(&experiment_name, &test_scenario, &context.namespace)
@@ -63,7 +68,7 @@ fn main() {
     let config = parse_configuration_options();
     rocket::ignite()
         .manage(WebContext::new(config))
-        .mount("/", routes![home, experiment, network, weights, marginals, factors, animation])
+        .mount("/", routes![home, experiment, network, weights, training, marginals, factors, animation])
         .mount("/static", StaticFiles::from("static"))
         .launch();
 }
@@ -0,0 +1,53 @@
+use bayes_star::common::graph::InferenceGraph;
+use bayes_star::common::resources::ResourceContext;
+use bayes_star::common::setup::parse_configuration_options;
+use bayes_star::model::frozen::{
+    freeze_implication, get_frozen_implications, unfreeze_implication, FrozenValue,
+};
+
+extern crate log;
+
+fn main() {
+    let config = parse_configuration_options();
+    let resources = ResourceContext::new(&config).expect("Couldn't create resources.");
+    let mut connection = resources.connection.lock().unwrap();
+    let namespace = &config.scenario_name;
+    if let Some(implication_key) = &config.freeze_implication {
+        let graph = InferenceGraph::new_mutable(namespace.clone()).expect("Couldn't create graph.");
+        let implications = graph
+            .get_all_implications(&mut connection)
+            .expect("Couldn't read implications.");
+        if !implications
+            .iter()
+            .any(|implication| &implication.unique_key() == implication_key)
+        {
+            panic!("No implication with key {}", implication_key);
+        }
+        let value = match (&config.frozen_probability, &config.frozen_weights) {
+            (Some(probability), None) => FrozenValue::Probability(*probability),
+            (None, Some(weights)) => FrozenValue::Weights(weights.clone()),
+            _ => panic!("Pass exactly one of --frozen_probability and --frozen_weights."),
+        };
+        match &value {
+            FrozenValue::Probability(_) => value.activation(implication_key).map(|_| ()),
+            FrozenValue::Weights(_) => value.exponential_weights(implication_key).map(|_| ()),
+        }
+        .expect("Invalid frozen value.");
+        freeze_implication(&mut connection, namespace, implication_key, &value)
+            .expect("Couldn't freeze implication.");
+        println!("froze {}", implication_key);
+    }
+    if let Some(implication_key) = &config.unfreeze_implication {
+        unfreeze_implication(&mut connection, namespace, implication_key)
+            .expect("Couldn't unfreeze implication.");
+        println!("unfroze {}", implication_key);
+    }
+    let frozen =
+        get_frozen_implications(&mut connection, namespace).expect("Couldn't list frozen implications.");
+    let mut keys: Vec<&String> = frozen.keys().collect();
+    keys.sort();
+    for key in keys {
+        println!("frozen {}: {}", key, frozen[key].describe());
+    }
+    println!("main finishes");
+}
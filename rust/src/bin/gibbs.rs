@@ -0,0 +1,29 @@
+use bayes_star::common::resources::ResourceContext;
+use bayes_star::common::setup::parse_configuration_options;
+use bayes_star::inference::gibbs::GibbsOptions;
+use bayes_star::inference::rounds::run_gibbs_inference;
+
+extern crate log;
+
+fn main() {
+    let config = parse_configuration_options();
+    let resources = ResourceContext::new(&config).expect("Couldn't create resources.");
+    let test_scenario = config
+        .test_scenario
+        .clone()
+        .expect("no test_scenario in config");
+    let mut connection = resources.connection.lock().unwrap();
+    let table = run_gibbs_inference(
+        &mut connection,
+        &config.scenario_name,
+        &test_scenario,
+        &config.checkpoint,
+        &GibbsOptions::new(&config),
+    )
+    .expect("Gibbs sampling failed.");
+    for (node, probability) in table.entries() {
+        let standard_error = table.get_standard_error(node).unwrap_or(0f64);
+        println!("gibbs {:.6} +- {:.6} {}", probability, standard_error, node);
+    }
+    println!("main finishes");
+}
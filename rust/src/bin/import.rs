@@ -0,0 +1,17 @@
+use bayes_star::common::import::run_import;
+use bayes_star::common::resources::ResourceContext;
+use bayes_star::common::setup::parse_configuration_options;
+
+extern crate log;
+
+fn main() {
+    let config = parse_configuration_options();
+    let resources = ResourceContext::new(&config).expect("Couldn't create resources.");
+    let mut connection = resources.connection.lock().unwrap();
+    let summary = run_import(&mut connection, &config).expect("Import failed.");
+    println!(
+        "imported entities {}, facts {} (training {}, test {})",
+        summary.entities, summary.facts, summary.training, summary.test
+    );
+    println!("main finishes");
+}
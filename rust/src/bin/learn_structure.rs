@@ -0,0 +1,22 @@
+use bayes_star::common::resources::ResourceContext;
+use bayes_star::common::setup::parse_configuration_options;
+use bayes_star::common::structure::learn_structure;
+
+extern crate log;
+
+fn main() {
+    let config = parse_configuration_options();
+    let resources = ResourceContext::new(&config).expect("Couldn't create resources.");
+    let mut connection = resources.connection.lock().unwrap();
+    let rules = learn_structure(&mut connection, &config).expect("Structure learning failed.");
+    for rule in &rules {
+        println!(
+            "{:<8} {:<10.6} {:<8} {}",
+            if rule.accepted { "accepted" } else { "rejected" },
+            rule.gain,
+            rule.examples,
+            rule.implication.unique_key()
+        );
+    }
+    println!("main finishes");
+}
@@ -9,10 +9,17 @@ fn main() {
     let resources = ResourceContext::new(&config).expect("Couldn't create resources.");
     let test_scenario = config.test_scenario.expect("no test_scenario in config");
     let mut connection = resources.connection.lock().unwrap();
-    let marginal_tables = run_inference_rounds(&mut connection, &config.scenario_name, &test_scenario)
-        .expect("Testing failed.");
+    let (marginal_tables, report) = run_inference_rounds(
+        &mut connection,
+        &config.scenario_name,
+        &test_scenario,
+        &config.checkpoint,
+        &resources.convergence,
+    )
+    .expect("Testing failed.");
     for marginal_table in &marginal_tables {
         println!("table {:?}", marginal_table);
     }
+    println!("convergence {:?}", report);
     println!("main finishes");
 }
@@ -11,6 +11,6 @@ fn main() {
     let config = parse_configuration_options();
     let resources = ResourceContext::new(&config).expect("Couldn't create resources.");
     let scenario_maker = ScenarioMakerFactory::new_shared(&config.scenario_name).unwrap();
-    setup_and_train(&resources, scenario_maker.borrow(), &config.scenario_name).expect("Error in training.");
+    setup_and_train(&resources, scenario_maker.borrow(), &config).expect("Error in training.");
     trace!("program done");
 }
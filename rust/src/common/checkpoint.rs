@@ -0,0 +1,140 @@
+use std::error::Error;
+
+use redis::Connection;
+
+use crate::{
+    baseline::model::MonolithicBayes,
+    common::{
+        prior::RootPriors,
+        redis::{is_member, key_copy, key_delete, namespace_keys, set_add, set_members},
+        setup::CommandLineOptions,
+    },
+    model::{
+        exponential::DOMAIN_BIAS_KEY,
+        factory::FactorModelFactory,
+        frozen::FROZEN_KEY,
+        interaction::{INTERACTIONS_KEY, INTERACTION_ORDER_KEY},
+        noisy_or::NoisyOrParameters,
+        weights::ExponentialWeights,
+    },
+};
+
+pub const CHECKPOINTS_KEY: &str = "checkpoints";
+/// Starts the names of the scratch checkpoints that training and evaluation make for themselves,
+/// e.g., those of the parallel training workers, which saved checkpoints must not collide with.
+pub const RESERVED_CHECKPOINT_PREFIX: &str = "__";
+
+/// The namespace that holds the model state of `checkpoint`, or `namespace` itself for the live
+/// model. The graph and the facts are always read from `namespace`.
+pub fn checkpoint_namespace(namespace: &str, checkpoint: &Option<String>) -> String {
+    match checkpoint {
+        Some(name) => format!("{}@{}", namespace, name),
+        None => namespace.to_string(),
+    }
+}
+
+/// The model namespace that `--warm_start_namespace` and `--warm_start_checkpoint` name, if any.
+/// A checkpoint alone names a checkpoint of the namespace being trained.
+pub fn warm_start_namespace(options: &CommandLineOptions) -> Option<String> {
+    let namespace = match (&options.warm_start_namespace, &options.warm_start_checkpoint) {
+        (Some(namespace), _) => namespace,
+        (None, Some(_)) => &options.scenario_name,
+        (None, None) => return None,
+    };
+    Some(checkpoint_namespace(namespace, &options.warm_start_checkpoint))
+}
+
+/// Every key that a trained model reads or writes, including optimizer state.
+fn model_state_keys(
+    connection: &mut Connection,
+    namespace: &str,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut result: Vec<String> = vec![
+        FactorModelFactory::FACTOR_MODEL_KEY,
+        ExponentialWeights::WEIGHTS_KEY,
+        INTERACTION_ORDER_KEY,
+        INTERACTIONS_KEY,
+        DOMAIN_BIAS_KEY,
+        NoisyOrParameters::PARAMETERS_KEY,
+        MonolithicBayes::COUNTS_KEY,
+        MonolithicBayes::IMPLICATIONS_KEY,
+        RootPriors::PRIORS_KEY,
+        FROZEN_KEY,
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect();
+    result.extend(namespace_keys(connection, namespace, "optimizer:*")?);
+    Ok(result)
+}
+
+pub fn clear_model_state(connection: &mut Connection, namespace: &str) -> Result<(), Box<dyn Error>> {
+    for key in model_state_keys(connection, namespace)? {
+        key_delete(connection, namespace, &key)?;
+    }
+    Ok(())
+}
+
+/// Replaces the model state of `to_namespace` with a copy of that of `from_namespace`.
+pub fn copy_model_state(
+    connection: &mut Connection,
+    from_namespace: &str,
+    to_namespace: &str,
+) -> Result<(), Box<dyn Error>> {
+    clear_model_state(connection, to_namespace)?;
+    for key in model_state_keys(connection, from_namespace)? {
+        key_copy(connection, from_namespace, to_namespace, &key)?;
+    }
+    Ok(())
+}
+
+/// Copies the current model state of `namespace` into the checkpoint `name`, replacing it.
+pub fn save_checkpoint(
+    connection: &mut Connection,
+    namespace: &str,
+    name: &str,
+) -> Result<(), Box<dyn Error>> {
+    if name.starts_with(RESERVED_CHECKPOINT_PREFIX) {
+        return Err(format!(
+            "Checkpoint names starting with {} are reserved: {}",
+            RESERVED_CHECKPOINT_PREFIX, name
+        )
+        .into());
+    }
+    let target = checkpoint_namespace(namespace, &Some(name.to_string()));
+    copy_model_state(connection, namespace, &target)?;
+    set_add(connection, namespace, CHECKPOINTS_KEY, name)?;
+    Ok(())
+}
+
+/// An error unless `name` is a saved checkpoint of `namespace`.
+pub fn check_checkpoint(
+    connection: &mut Connection,
+    namespace: &str,
+    name: &str,
+) -> Result<(), Box<dyn Error>> {
+    if !is_member(connection, namespace, CHECKPOINTS_KEY, name)? {
+        return Err(format!("No checkpoint named {}", name).into());
+    }
+    Ok(())
+}
+
+/// Replaces the current model state of `namespace` with the checkpoint `name`.
+pub fn restore_checkpoint(
+    connection: &mut Connection,
+    namespace: &str,
+    name: &str,
+) -> Result<(), Box<dyn Error>> {
+    check_checkpoint(connection, namespace, name)?;
+    let source = checkpoint_namespace(namespace, &Some(name.to_string()));
+    copy_model_state(connection, &source, namespace)
+}
+
+pub fn get_checkpoints(
+    connection: &mut Connection,
+    namespace: &str,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut result = set_members(connection, namespace, CHECKPOINTS_KEY)?;
+    result.sort();
+    Ok(result)
+}
@@ -0,0 +1,312 @@
+use std::{collections::HashSet, error::Error, sync::Arc};
+
+use redis::Connection;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    baseline::model::MonolithicBayes,
+    common::{
+        interface::BeliefTable,
+        model::InferenceModel,
+        proposition_db::RedisBeliefTable,
+        train::TrainingPlan,
+    },
+    inference::{inference::ConvergenceOptions, rounds::run_inference_with_stored_evidence},
+    model::objects::Proposition,
+};
+
+const CALIBRATION_BINS: usize = 10;
+
+/// One held-out proposition, with the marginal predicted for it and its stored gold value.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EvaluationExample {
+    pub proposition: String,
+    pub predicted: f64,
+    pub gold: f64,
+    /// Whether belief propagation converged for this example. If not, `predicted` may be from
+    /// an oscillating run.
+    pub converged: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CalibrationBin {
+    pub lower: f64,
+    pub upper: f64,
+    pub count: usize,
+    pub mean_predicted: f64,
+    pub mean_gold: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EvaluationReport {
+    pub examples: Vec<EvaluationExample>,
+    pub accuracy: f64,
+    pub log_loss: f64,
+    pub brier_score: f64,
+    /// None when the gold labels contain only one class.
+    pub auc: Option<f64>,
+    pub calibration: Vec<CalibrationBin>,
+    /// The number of examples whose belief propagation did not converge.
+    pub unconverged: usize,
+}
+
+fn compute_auc(examples: &Vec<EvaluationExample>) -> Option<f64> {
+    let positives: Vec<f64> = examples
+        .iter()
+        .filter(|e| e.gold >= 0.5)
+        .map(|e| e.predicted)
+        .collect();
+    let negatives: Vec<f64> = examples
+        .iter()
+        .filter(|e| e.gold < 0.5)
+        .map(|e| e.predicted)
+        .collect();
+    if positives.is_empty() || negatives.is_empty() {
+        return None;
+    }
+    let mut wins = 0f64;
+    for p in &positives {
+        for n in &negatives {
+            if p > n {
+                wins += 1f64;
+            } else if p == n {
+                wins += 0.5f64;
+            }
+        }
+    }
+    Some(wins / (positives.len() * negatives.len()) as f64)
+}
+
+fn compute_calibration(examples: &Vec<EvaluationExample>) -> Vec<CalibrationBin> {
+    let mut result = vec![];
+    for bin in 0..CALIBRATION_BINS {
+        let lower = bin as f64 / CALIBRATION_BINS as f64;
+        let upper = (bin + 1) as f64 / CALIBRATION_BINS as f64;
+        let members: Vec<&EvaluationExample> = examples
+            .iter()
+            .filter(|e| {
+                let index = ((e.predicted * CALIBRATION_BINS as f64) as usize).min(CALIBRATION_BINS - 1);
+                index == bin
+            })
+            .collect();
+        let count = members.len();
+        let denominator = count.max(1) as f64;
+        result.push(CalibrationBin {
+            lower,
+            upper,
+            count,
+            mean_predicted: members.iter().map(|e| e.predicted).sum::<f64>() / denominator,
+            mean_gold: members.iter().map(|e| e.gold).sum::<f64>() / denominator,
+        });
+    }
+    result
+}
+
+impl EvaluationReport {
+    pub fn new(examples: Vec<EvaluationExample>) -> EvaluationReport {
+        let epsilon = 1e-12;
+        let denominator = examples.len().max(1) as f64;
+        let mut correct = 0f64;
+        let mut log_loss = 0f64;
+        let mut brier_score = 0f64;
+        for example in &examples {
+            if (example.predicted >= 0.5) == (example.gold >= 0.5) {
+                correct += 1f64;
+            }
+            let p = example.predicted.clamp(epsilon, 1f64 - epsilon);
+            log_loss -= example.gold * p.ln() + (1f64 - example.gold) * (1f64 - p).ln();
+            brier_score += (example.predicted - example.gold).powi(2);
+        }
+        let auc = compute_auc(&examples);
+        let calibration = compute_calibration(&examples);
+        let unconverged = examples.iter().filter(|e| !e.converged).count();
+        EvaluationReport {
+            examples,
+            accuracy: correct / denominator,
+            log_loss: log_loss / denominator,
+            brier_score: brier_score / denominator,
+            auc,
+            calibration,
+            unconverged,
+        }
+    }
+
+    pub fn render_text(&self) -> String {
+        let mut buffer = String::new();
+        buffer += &format!("examples     {}\n", self.examples.len());
+        buffer += &format!("accuracy     {:.6}\n", self.accuracy);
+        buffer += &format!("log_loss     {:.6}\n", self.log_loss);
+        buffer += &format!("brier_score  {:.6}\n", self.brier_score);
+        match self.auc {
+            Some(auc) => buffer += &format!("auc          {:.6}\n", auc),
+            None => buffer += "auc          undefined (one class only)\n",
+        }
+        buffer += &format!("unconverged  {}\n", self.unconverged);
+        buffer += "\ncalibration\n";
+        buffer += &format!(
+            "{:<12} {:<8} {:<14} {:<14}\n",
+            "bin", "count", "mean_predicted", "mean_gold"
+        );
+        for bin in &self.calibration {
+            buffer += &format!(
+                "{:<12} {:<8} {:<14.6} {:<14.6}\n",
+                format!("{:.1}-{:.1}", bin.lower, bin.upper),
+                bin.count,
+                bin.mean_predicted,
+                bin.mean_gold
+            );
+        }
+        buffer
+    }
+}
+
+/// Infers the marginal of `target`, using every other stored fact in its proposition graph as
+/// evidence, except for the propositions in `held_out`. Also says whether inference converged.
+pub fn predict_held_out_marginal(
+    connection: &mut Connection,
+    model: &Arc<InferenceModel>,
+    proposition_db: &Box<dyn BeliefTable>,
+    held_out: &HashSet<Proposition>,
+    target: &Proposition,
+    convergence: &ConvergenceOptions,
+) -> Result<(f64, bool), Box<dyn Error>> {
+    let mut excluded = held_out.clone();
+    excluded.insert(target.clone());
+    let (table, report) = run_inference_with_stored_evidence(
+        connection,
+        model,
+        proposition_db,
+        target,
+        &excluded,
+        convergence,
+    )?;
+    let marginal = table
+        .get_marginal(target)
+        .ok_or("Target is missing from the marginal table.")?;
+    Ok((marginal, report.converged))
+}
+
+/// How much the trained model improves on the count-based baseline. Positive is better for every
+/// field: the log loss and Brier score gains are reductions.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BaselineComparison {
+    pub model: EvaluationReport,
+    pub baseline: EvaluationReport,
+    pub accuracy_gain: f64,
+    pub log_loss_gain: f64,
+    pub brier_score_gain: f64,
+    pub auc_gain: Option<f64>,
+}
+
+impl BaselineComparison {
+    pub fn new(model: EvaluationReport, baseline: EvaluationReport) -> BaselineComparison {
+        let auc_gain = match (model.auc, baseline.auc) {
+            (Some(model_auc), Some(baseline_auc)) => Some(model_auc - baseline_auc),
+            _ => None,
+        };
+        BaselineComparison {
+            accuracy_gain: model.accuracy - baseline.accuracy,
+            log_loss_gain: baseline.log_loss - model.log_loss,
+            brier_score_gain: baseline.brier_score - model.brier_score,
+            auc_gain,
+            model,
+            baseline,
+        }
+    }
+
+    pub fn render_text(&self) -> String {
+        let mut buffer = String::new();
+        buffer += "model\n";
+        buffer += &self.model.render_text();
+        buffer += "\nbaseline\n";
+        buffer += &self.baseline.render_text();
+        buffer += "\ngain over baseline\n";
+        buffer += &format!("accuracy     {:+.6}\n", self.accuracy_gain);
+        buffer += &format!("log_loss     {:+.6}\n", self.log_loss_gain);
+        buffer += &format!("brier_score  {:+.6}\n", self.brier_score_gain);
+        match self.auc_gain {
+            Some(auc_gain) => buffer += &format!("auc          {:+.6}\n", auc_gain),
+            None => buffer += "auc          undefined\n",
+        }
+        buffer
+    }
+}
+
+pub fn run_evaluation(
+    connection: &mut Connection,
+    namespace: &str,
+    checkpoint: &Option<String>,
+    convergence: &ConvergenceOptions,
+) -> Result<EvaluationReport, Box<dyn Error>> {
+    let model =
+        InferenceModel::new_shared_at_checkpoint(connection, namespace.to_string(), checkpoint)?;
+    run_evaluation_with_model(connection, namespace, &model, convergence)
+}
+
+/// Evaluates the trained model and a `MonolithicBayes` baseline counted over the same training
+/// queue, on the same held-out propositions.
+pub fn run_baseline_comparison(
+    connection: &mut Connection,
+    namespace: &str,
+    checkpoint: &Option<String>,
+    convergence: &ConvergenceOptions,
+) -> Result<BaselineComparison, Box<dyn Error>> {
+    let model_report = run_evaluation(connection, namespace, checkpoint, convergence)?;
+    let baseline_report = run_baseline_evaluation(connection, namespace, convergence);
+    MonolithicBayes::clear_fit(connection, namespace)?;
+    Ok(BaselineComparison::new(model_report, baseline_report?))
+}
+
+fn run_baseline_evaluation(
+    connection: &mut Connection,
+    namespace: &str,
+    convergence: &ConvergenceOptions,
+) -> Result<EvaluationReport, Box<dyn Error>> {
+    let baseline = MonolithicBayes::fit(connection, namespace.to_string())?;
+    let baseline_model = InferenceModel::new_shared_with_model(namespace.to_string(), baseline)?;
+    run_evaluation_with_model(connection, namespace, &baseline_model, convergence)
+}
+
+pub fn run_evaluation_with_model(
+    connection: &mut Connection,
+    namespace: &str,
+    model: &Arc<InferenceModel>,
+    convergence: &ConvergenceOptions,
+) -> Result<EvaluationReport, Box<dyn Error>> {
+    let proposition_db = RedisBeliefTable::new_mutable(connection, namespace.to_string())?;
+    let plan = TrainingPlan::new(namespace.to_string())?;
+    let test_questions = plan.get_test_questions(connection)?;
+    let held_out: HashSet<Proposition> = test_questions.iter().cloned().collect();
+    let mut examples = vec![];
+    for proposition in &test_questions {
+        let gold = match proposition_db.get_proposition_probability(connection, proposition)? {
+            Some(gold) => gold,
+            None => {
+                warn!("No gold value stored for {:?}, skipping.", proposition);
+                continue;
+            }
+        };
+        let (predicted, converged) = predict_held_out_marginal(
+            connection,
+            model,
+            &proposition_db,
+            &held_out,
+            proposition,
+            convergence,
+        )?;
+        trace!(
+            "evaluation: {:?} predicted {} gold {} converged {}",
+            proposition,
+            predicted,
+            gold,
+            converged
+        );
+        examples.push(EvaluationExample {
+            proposition: proposition.hash_string(),
+            predicted,
+            gold,
+            converged,
+        });
+    }
+    Ok(EvaluationReport::new(examples))
+}
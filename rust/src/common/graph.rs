@@ -6,7 +6,7 @@ use super::{
 use crate::{
     common::{
         interface::BeliefTable,
-        redis::{get_value, is_member, set_add, set_members},
+        redis::{get_value, is_member, set_add, set_members, set_remove},
     },
     model::{
         self,
@@ -108,6 +108,59 @@ impl InferenceGraph {
             .collect()
     }
 
+    /// Marks the relation as closed-world: its groundings that have no stored probability are
+    /// false. This lasts until `unregister_closed_world`. A `RedisBeliefTable` reads the
+    /// closed-world relations when it is created, so create it after registering.
+    pub fn register_closed_world(
+        &mut self,
+        connection: &mut Connection,
+        relation_name: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        set_add(
+            connection,
+            &self.namespace,
+            &Self::closed_world_set_name(),
+            relation_name,
+        )?;
+        Ok(())
+    }
+
+    /// Makes the relation open-world again: its groundings that have no stored probability are
+    /// unknown.
+    pub fn unregister_closed_world(
+        &mut self,
+        connection: &mut Connection,
+        relation_name: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        set_remove(
+            connection,
+            &self.namespace,
+            &Self::closed_world_set_name(),
+            relation_name,
+        )?;
+        Ok(())
+    }
+
+    pub fn is_closed_world(
+        &self,
+        connection: &mut Connection,
+        relation_name: &str,
+    ) -> Result<bool, Box<dyn Error>> {
+        is_member(
+            connection,
+            &self.namespace,
+            &Self::closed_world_set_name(),
+            relation_name,
+        )
+    }
+
+    pub fn get_closed_world_relations(
+        &self,
+        connection: &mut Connection,
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        set_members(connection, &self.namespace, &Self::closed_world_set_name())
+    }
+
     pub fn register_domain(
         &mut self,
         connection: &mut Connection,
@@ -204,6 +257,10 @@ impl InferenceGraph {
         "relations".to_string()
     }
 
+    fn closed_world_set_name() -> String {
+        "closed_world_relations".to_string()
+    }
+
     fn experiment_set_name() -> String {
         "experiments".to_string()
     }
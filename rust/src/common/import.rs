@@ -0,0 +1,376 @@
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fs,
+    path::Path,
+};
+
+use redis::Connection;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::{
+        graph::InferenceGraph, proposition_db::RedisBeliefTable, setup::CommandLineOptions,
+        train::TrainingPlan,
+    },
+    model::{
+        choose::extract_backimplications_from_proposition,
+        creators::{constant, proposition, role},
+        objects::{existence_predicate_name, Entity, ImplicationFactor, Proposition, Relation},
+    },
+};
+
+/// One line of an entities file.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EntityRecord {
+    pub domain: String,
+    pub name: String,
+}
+
+/// One line of a facts file. `roles` are (role name, entity name) pairs, in the order of the
+/// relation's argument types. `split` is "train", "test" or "none". `weight` scales the training
+/// update of the fact, and is 1 if omitted.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FactRecord {
+    pub relation: String,
+    pub roles: Vec<(String, String)>,
+    pub probability: f64,
+    pub split: String,
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+}
+
+fn default_weight() -> f64 {
+    1f64
+}
+
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    pub entities: usize,
+    pub facts: usize,
+    pub training: usize,
+    pub test: usize,
+}
+
+fn is_jsonl(file_name: &str) -> bool {
+    let extension = Path::new(file_name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("");
+    extension == "jsonl" || extension == "json"
+}
+
+/// Splits a CSV file into rows of fields, skipping the header and blank lines. Fields are not
+/// quoted, so they cannot contain commas.
+fn read_csv_rows(
+    contents: &str,
+    expected_header: &[&str],
+) -> Result<Vec<(usize, Vec<String>)>, Box<dyn Error>> {
+    let mut lines = contents.lines().enumerate();
+    let header: Vec<String> = match lines.next() {
+        Some((_, line)) => line.split(',').map(|f| f.trim().to_string()).collect(),
+        None => return Ok(vec![]),
+    };
+    if header != expected_header {
+        return Err(format!(
+            "CSV header should be {:?}, found {:?}",
+            expected_header, header
+        )
+        .into());
+    }
+    let mut result = vec![];
+    for (index, line) in lines {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields: Vec<String> = line.split(',').map(|f| f.trim().to_string()).collect();
+        if fields.len() != expected_header.len() {
+            return Err(format!(
+                "Line {}: expected {} fields",
+                index + 1,
+                expected_header.len()
+            )
+            .into());
+        }
+        result.push((index + 1, fields));
+    }
+    Ok(result)
+}
+
+/// Reads `domain,name` CSV or JSON Lines.
+pub fn read_entity_records(file_name: &str) -> Result<Vec<EntityRecord>, Box<dyn Error>> {
+    let contents = fs::read_to_string(file_name)?;
+    let mut result = vec![];
+    if is_jsonl(file_name) {
+        for (index, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: EntityRecord = serde_json::from_str(line)
+                .map_err(|e| format!("{} line {}: {}", file_name, index + 1, e))?;
+            result.push(record);
+        }
+    } else {
+        for (_, fields) in read_csv_rows(&contents, &["domain", "name"])? {
+            result.push(EntityRecord {
+                domain: fields[0].clone(),
+                name: fields[1].clone(),
+            });
+        }
+    }
+    Ok(result)
+}
+
+/// Reads `relation,roles,probability,split` CSV, optionally with a fifth `weight` column, where
+/// `roles` looks like `sub=jack;obj=jill`, or JSON Lines.
+pub fn read_fact_records(file_name: &str) -> Result<Vec<FactRecord>, Box<dyn Error>> {
+    let contents = fs::read_to_string(file_name)?;
+    let mut result = vec![];
+    if is_jsonl(file_name) {
+        for (index, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: FactRecord = serde_json::from_str(line)
+                .map_err(|e| format!("{} line {}: {}", file_name, index + 1, e))?;
+            result.push(record);
+        }
+    } else {
+        let weighted = contents
+            .lines()
+            .next()
+            .map_or(false, |header| header.trim().ends_with(",weight"));
+        let header: &[&str] = if weighted {
+            &["relation", "roles", "probability", "split", "weight"]
+        } else {
+            &["relation", "roles", "probability", "split"]
+        };
+        for (line, fields) in read_csv_rows(&contents, header)? {
+            let mut roles = vec![];
+            for binding in fields[1].split(';') {
+                let (role_name, entity_name) = binding.split_once('=').ok_or(format!(
+                    "{} line {}: bad role binding {}",
+                    file_name, line, binding
+                ))?;
+                roles.push((role_name.trim().to_string(), entity_name.trim().to_string()));
+            }
+            let probability = fields[2]
+                .parse::<f64>()
+                .map_err(|e| format!("{} line {}: {}", file_name, line, e))?;
+            let weight = if weighted {
+                fields[4]
+                    .parse::<f64>()
+                    .map_err(|e| format!("{} line {}: {}", file_name, line, e))?
+            } else {
+                default_weight()
+            };
+            result.push(FactRecord {
+                relation: fields[0].clone(),
+                roles,
+                probability,
+                split: fields[3].clone(),
+                weight,
+            });
+        }
+    }
+    Ok(result)
+}
+
+/// The registered relations with this name whose argument domains, in order, are domains of the
+/// named entities, i.e., that the fact could be about.
+fn matching_relations<'a>(
+    relations: &'a [Relation],
+    relation_name: &str,
+    entity_names: &[&String],
+    entities: &HashSet<(String, String)>,
+) -> Vec<&'a Relation> {
+    relations
+        .iter()
+        .filter(|relation| {
+            relation.relation_name == relation_name
+                && relation.types.len() == entity_names.len()
+                && relation
+                    .types
+                    .iter()
+                    .zip(entity_names)
+                    .all(|(argument, name)| {
+                        entities.contains(&(argument.domain.clone(), (*name).clone()))
+                    })
+        })
+        .collect()
+}
+
+/// The role names that the registered implications use for each relation, sorted, from their
+/// premises and conclusions.
+fn implication_role_names(
+    implications: &[ImplicationFactor],
+) -> HashMap<String, HashSet<Vec<String>>> {
+    let mut result: HashMap<String, HashSet<Vec<String>>> = HashMap::new();
+    for implication in implications {
+        for predicate in implication
+            .premise
+            .terms
+            .iter()
+            .chain(std::iter::once(&implication.conclusion))
+        {
+            let relation_name = &predicate.relation.relation_name;
+            if *relation_name == existence_predicate_name() {
+                continue;
+            }
+            let mut role_names = predicate.role_names();
+            role_names.sort();
+            result
+                .entry(relation_name.clone())
+                .or_default()
+                .insert(role_names);
+        }
+    }
+    result
+}
+
+/// Checks a fact against the registered relations, the role names of the implications and the
+/// known entities, by (domain, name), and builds its proposition.
+fn fact_to_proposition(
+    record: &FactRecord,
+    relations: &[Relation],
+    role_names: &HashMap<String, HashSet<Vec<String>>>,
+    entities: &HashSet<(String, String)>,
+) -> Result<Proposition, Box<dyn Error>> {
+    if !(0f64..=1f64).contains(&record.probability) {
+        return Err(format!("Probability out of range: {:?}", record).into());
+    }
+    if !(record.weight.is_finite() && record.weight >= 0f64) {
+        return Err(format!("Weight must be a non-negative number: {:?}", record).into());
+    }
+    // A relation that no implication uses yet, e.g., before structure learning, can have any
+    // role names. Otherwise a mistyped role would match no implication, and the fact would
+    // silently become a disconnected root.
+    if let Some(known) = role_names.get(&record.relation) {
+        let mut fact_role_names: Vec<String> = record
+            .roles
+            .iter()
+            .map(|(role_name, _)| role_name.clone())
+            .collect();
+        fact_role_names.sort();
+        if !known.contains(&fact_role_names) {
+            return Err(format!(
+                "No registered implication uses {} with roles {:?}: {:?}",
+                record.relation, fact_role_names, record
+            )
+            .into());
+        }
+    }
+    let entity_names: Vec<&String> = record.roles.iter().map(|(_, name)| name).collect();
+    let relation = match matching_relations(relations, &record.relation, &entity_names, entities)[..]
+    {
+        [relation] => relation,
+        [] => {
+            return Err(format!(
+                "No registered relation {} has known entities {:?} in its domains: {:?}",
+                record.relation, entity_names, record
+            )
+            .into())
+        }
+        _ => {
+            return Err(format!(
+                "The entities of {:?} are in the domains of more than one relation {}",
+                record, record.relation
+            )
+            .into())
+        }
+    };
+    let roles = record
+        .roles
+        .iter()
+        .zip(&relation.types)
+        .map(|((role_name, entity_name), argument)| {
+            role(
+                role_name.clone(),
+                constant(argument.domain.clone(), entity_name.clone()),
+            )
+        })
+        .collect();
+    Ok(proposition(relation.clone(), roles))
+}
+
+/// Loads `--entities_file` and `--facts_file` into the namespace. The relations must be
+/// registered already, e.g., by a scenario. Facts of relations with no implications into them
+/// get an existence backlink, as the scenarios do for their root facts. The first test fact
+/// becomes the target. Both files are read and checked before anything is written.
+pub fn run_import(
+    connection: &mut Connection,
+    options: &CommandLineOptions,
+) -> Result<ImportSummary, Box<dyn Error>> {
+    let namespace = options.scenario_name.clone();
+    let mut graph = InferenceGraph::new_mutable(namespace.clone())?;
+    let proposition_db = RedisBeliefTable::new_mutable(connection, namespace.clone())?;
+    let mut plan = TrainingPlan::new(namespace.clone())?;
+    let relations = graph.get_all_relations(connection)?;
+    let role_names = implication_role_names(&graph.get_all_implications(connection)?);
+    let mut summary = ImportSummary::default();
+    let mut entities: HashSet<(String, String)> = HashSet::new();
+    for domain in graph.get_all_domains(connection)? {
+        for entity in graph.get_entities_in_domain(connection, &domain)? {
+            entities.insert((entity.domain, entity.name));
+        }
+    }
+    let entity_records = match &options.entities_file {
+        Some(file_name) => read_entity_records(file_name)?,
+        None => vec![],
+    };
+    for record in &entity_records {
+        let known_domain = relations
+            .iter()
+            .any(|relation| relation.types.iter().any(|t| t.domain == record.domain));
+        if !known_domain {
+            return Err(format!("No registered relation uses domain {}", record.domain).into());
+        }
+        entities.insert((record.domain.clone(), record.name.clone()));
+    }
+    let fact_records = match &options.facts_file {
+        Some(file_name) => read_fact_records(file_name)?,
+        None => vec![],
+    };
+    let mut propositions = vec![];
+    for record in &fact_records {
+        propositions.push(fact_to_proposition(
+            record,
+            &relations,
+            &role_names,
+            &entities,
+        )?);
+        if !["train", "test", "none"].contains(&record.split.as_str()) {
+            return Err(format!("Unknown split {} in {:?}", record.split, record).into());
+        }
+    }
+    for record in entity_records {
+        graph.register_domain(connection, &record.domain)?;
+        graph.store_entity(
+            connection,
+            &Entity {
+                domain: record.domain,
+                name: record.name,
+            },
+        )?;
+        summary.entities += 1;
+    }
+    let mut has_target = false;
+    for (record, fact) in fact_records.iter().zip(&propositions) {
+        proposition_db.store_proposition_probability(connection, fact, record.probability)?;
+        if extract_backimplications_from_proposition(connection, &graph, fact)?.is_empty() {
+            graph.ensure_existence_backlinks_for_proposition(connection, fact)?;
+        }
+        let is_training = record.split == "train";
+        let is_test = record.split == "test";
+        plan.maybe_add_weighted_to_training(connection, is_training, fact, record.weight)?;
+        plan.maybe_add_to_test(connection, is_test, fact)?;
+        if is_test && !has_target {
+            graph.register_target(connection, fact)?;
+            has_target = true;
+        }
+        summary.facts += 1;
+        summary.training += is_training as usize;
+        summary.test += is_test as usize;
+    }
+    Ok(summary)
+}
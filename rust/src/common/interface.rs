@@ -7,7 +7,23 @@ use crate::model::objects::{PredicateGroup, ImplicationFactor, Predicate, Propos
 use super::{graph::InferenceGraph, model::InferenceModel, train::TrainingPlan, redis::RedisManager, resources::ResourceContext};
 
 pub struct TrainStatistics {
-    pub loss: f64,
+    pub log_loss: f64,
+    pub squared_error: f64,
+}
+
+impl TrainStatistics {
+    /// Scores the probability predicted *before* the update against the gold probability.
+    pub fn new(predicted_probability: f64, gold_probability: f64) -> TrainStatistics {
+        let epsilon = 1e-12;
+        let p = predicted_probability.clamp(epsilon, 1f64 - epsilon);
+        let log_loss =
+            -(gold_probability * p.ln() + (1f64 - gold_probability) * (1f64 - p).ln());
+        let squared_error = (predicted_probability - gold_probability).powi(2);
+        TrainStatistics {
+            log_loss,
+            squared_error,
+        }
+    }
 }
 
 pub struct PredictStatistics {
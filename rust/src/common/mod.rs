@@ -4,7 +4,16 @@ pub mod model;
 pub mod graph;
 pub mod proposition_db;
 pub mod train;
+pub mod premise;
+pub mod prior;
+pub mod evaluate;
+pub mod structure;
+pub mod import;
+pub mod checkpoint;
+pub mod parallel;
+pub mod negative;
 pub mod resources;
+pub mod random;
 pub mod setup;
 pub mod test;
 pub mod logging;
\ No newline at end of file
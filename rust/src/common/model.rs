@@ -3,7 +3,8 @@ use crate::{
     inference::graph::PropositionFactor,
     model::{
         self,
-        exponential::ExponentialModel,
+        factory::FactorModelFactory,
+        choose::extract_backimplications_from_proposition,
         objects::{Domain, Entity, ImplicationFactor, Predicate, PredicateGroup, Proposition},
     },
 };
@@ -11,8 +12,10 @@ use redis::{Commands, Connection};
 use std::{cell::RefCell, collections::HashMap, error::Error, rc::Rc, sync::Arc};
 
 use super::{
+    checkpoint::{check_checkpoint, checkpoint_namespace},
     graph::InferenceGraph,
     interface::{PredictStatistics, TrainStatistics},
+    prior::RootPriors,
     proposition_db::RedisBeliefTable,
     redis::RedisManager,
     resources::ResourceContext,
@@ -21,13 +24,94 @@ use super::{
 pub struct InferenceModel {
     pub graph: Arc<InferenceGraph>,
     pub model: Arc<dyn FactorModel>,
+    pub priors: RootPriors,
 }
 
 impl InferenceModel {
-    pub fn new_shared(namespace: String) -> Result<Arc<Self>, Box<dyn Error>> {
+    pub fn new_shared(
+        connection: &mut Connection,
+        namespace: String,
+    ) -> Result<Arc<Self>, Box<dyn Error>> {
+        let graph = InferenceGraph::new_shared(namespace.clone())?;
+        let model = FactorModelFactory::new_shared(connection, namespace.clone())?;
+        let priors = RootPriors::new(namespace)?;
+        Ok(Arc::new(InferenceModel {
+            graph,
+            model,
+            priors,
+        }))
+    }
+
+    /// Reads the model state from `checkpoint` if given, and the graph from `namespace`. A
+    /// checkpoint that was never saved is an error.
+    pub fn new_shared_at_checkpoint(
+        connection: &mut Connection,
+        namespace: String,
+        checkpoint: &Option<String>,
+    ) -> Result<Arc<Self>, Box<dyn Error>> {
+        if let Some(name) = checkpoint {
+            check_checkpoint(connection, &namespace, name)?;
+        }
+        let model_namespace = checkpoint_namespace(&namespace, checkpoint);
+        Self::new_shared_in_model_namespace(connection, namespace, model_namespace)
+    }
+
+    /// Reads the model state from `model_namespace`, e.g., that of a training worker, and the
+    /// graph from `namespace`.
+    pub fn new_shared_in_model_namespace(
+        connection: &mut Connection,
+        namespace: String,
+        model_namespace: String,
+    ) -> Result<Arc<Self>, Box<dyn Error>> {
+        let graph = InferenceGraph::new_shared(namespace)?;
+        let model = FactorModelFactory::new_shared(connection, model_namespace.clone())?;
+        let priors = RootPriors::new(model_namespace)?;
+        Ok(Arc::new(InferenceModel {
+            graph,
+            model,
+            priors,
+        }))
+    }
+
+    /// Uses `model` for the factors instead of the model that the namespace was trained with.
+    pub fn new_shared_with_model(
+        namespace: String,
+        model: Arc<dyn FactorModel>,
+    ) -> Result<Arc<Self>, Box<dyn Error>> {
         let graph = InferenceGraph::new_shared(namespace.clone())?;
-        let model = ExponentialModel::new_shared(namespace.clone())?;
-        Ok(Arc::new(InferenceModel { graph, model }))
+        let priors = RootPriors::new(namespace)?;
+        Ok(Arc::new(InferenceModel {
+            graph,
+            model,
+            priors,
+        }))
+    }
+
+    /// The probability of a root proposition before any evidence: its learned prior, or, for a
+    /// namespace trained before priors were learned, what its existence factor predicts.
+    pub fn root_probability(
+        &self,
+        connection: &mut Connection,
+        root: &Proposition,
+    ) -> Result<f64, Box<dyn Error>> {
+        if let Some(prior) = self.priors.get_prior(connection, root)? {
+            return Ok(prior);
+        }
+        let factors = extract_backimplications_from_proposition(connection, &self.graph, root)?;
+        if factors.is_empty() {
+            return Err(format!(
+                "No prior learned for {:?}, retrain the namespace to learn root priors",
+                root
+            )
+            .into());
+        }
+        // The premise of an existence factor is always true.
+        let probabilities = vec![1f64; factors.len()];
+        let context = FactorContext {
+            factor: factors,
+            probabilities,
+        };
+        Ok(self.model.predict(connection, &context)?.probability)
     }
 }
 
@@ -44,11 +128,19 @@ pub trait FactorModel {
         implication: &ImplicationFactor,
     ) -> Result<(), Box<dyn Error>>;
 
+    fn is_connection_initialized(
+        &self,
+        connection: &mut Connection,
+        implication: &ImplicationFactor,
+    ) -> Result<bool, Box<dyn Error>>;
+
+    /// Updates the model towards `probability`, with the update scaled by `example_weight`.
     fn train(
         &mut self,
         connection: &mut Connection,
         factor: &FactorContext,
         probability: f64,
+        example_weight: f64,
     ) -> Result<TrainStatistics, Box<dyn Error>>;
 
     fn predict(
@@ -0,0 +1,107 @@
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+};
+
+use rand::{rngs::StdRng, Rng};
+use redis::Connection;
+
+use crate::{
+    common::{
+        graph::InferenceGraph, proposition_db::RedisBeliefTable, random::keyed_rng,
+        train::TrainingExample,
+    },
+    model::{
+        creators::{constant, proposition},
+        objects::{Argument, Entity, Proposition},
+    },
+};
+
+/// How many random groundings are tried for each negative sample before giving up, e.g., when
+/// almost every grounding of the relation is stored.
+const MAX_ATTEMPTS: usize = 10;
+
+/// A random grounding of the relation of `example`, with each entity replaced by a random entity
+/// of the same domain.
+fn random_grounding(
+    rng: &mut StdRng,
+    entities: &HashMap<String, Vec<Entity>>,
+    example: &Proposition,
+) -> Proposition {
+    let roles = example
+        .predicate
+        .roles
+        .iter()
+        .map(|role| match &role.argument {
+            Argument::Constant(argument) => match entities.get(&argument.domain) {
+                Some(choices) if !choices.is_empty() => {
+                    let entity = &choices[rng.gen_range(0..choices.len())];
+                    role.do_substitution(constant(entity.domain.clone(), entity.name.clone()))
+                }
+                _ => role.clone(),
+            },
+            Argument::Variable(_) => role.clone(),
+        })
+        .collect();
+    proposition(example.predicate.relation.clone(), roles)
+}
+
+/// The examples of one epoch: each of `examples`, followed by about `negative_ratio` random
+/// groundings of its relation that are not stored, if the relation is closed-world. Those are
+/// false, so closed-world relations need not have every false grounding stored.
+pub fn add_negative_samples(
+    connection: &mut Connection,
+    graph: &InferenceGraph,
+    examples: &[TrainingExample],
+    negative_ratio: f64,
+    epoch: usize,
+) -> Result<Vec<TrainingExample>, Box<dyn Error>> {
+    let closed_world: HashSet<String> = graph
+        .get_closed_world_relations(connection)?
+        .into_iter()
+        .collect();
+    if closed_world.is_empty() || negative_ratio <= 0f64 {
+        return Ok(examples.to_vec());
+    }
+    let mut entities: HashMap<String, Vec<Entity>> = HashMap::new();
+    for domain in graph.get_all_domains(connection)? {
+        let members = graph.get_entities_in_domain(connection, &domain)?;
+        entities.insert(domain, members);
+    }
+    let mut rng = keyed_rng(&format!("negative samples {}", epoch));
+    let mut result = vec![];
+    let mut sampled = 0;
+    for example in examples {
+        result.push(example.clone());
+        let relation_name = &example.proposition.predicate.relation.relation_name;
+        if !closed_world.contains(relation_name) {
+            continue;
+        }
+        let fraction = negative_ratio.fract();
+        let count = negative_ratio.floor() as usize + (rng.gen::<f64>() < fraction) as usize;
+        for _ in 0..count {
+            for _ in 0..MAX_ATTEMPTS {
+                let negative = random_grounding(&mut rng, &entities, &example.proposition);
+                if !RedisBeliefTable::has_stored_probability(
+                    connection,
+                    &graph.namespace,
+                    &negative,
+                )? {
+                    result.push(TrainingExample {
+                        proposition: negative,
+                        weight: 1f64,
+                    });
+                    sampled += 1;
+                    break;
+                }
+            }
+        }
+    }
+    trace!(
+        "add_negative_samples - epoch {}: {} negatives for {} examples",
+        epoch,
+        sampled,
+        examples.len()
+    );
+    Ok(result)
+}
@@ -0,0 +1,271 @@
+use std::{collections::HashMap, error::Error, thread};
+
+use redis::Connection;
+
+use crate::{
+    baseline::model::MonolithicBayes,
+    common::{
+        checkpoint::{
+            checkpoint_namespace, clear_model_state, copy_model_state, RESERVED_CHECKPOINT_PREFIX,
+        },
+        graph::InferenceGraph,
+        model::InferenceModel,
+        premise::PremiseEstimator,
+        proposition_db::RedisBeliefTable,
+        redis::{map_get_all, map_insert, namespace_keys, set_add, set_members, RedisManager},
+        setup::CommandLineOptions,
+        train::{train_on_examples, ClassWeights, LossTotals, TrainingExample},
+    },
+    inference::inference::ConvergenceOptions,
+    model::{
+        factory::FactorModelFactory, interaction::INTERACTIONS_KEY, noisy_or::NoisyOrParameters,
+        optimizer::ADAM_STEPS_KEY, weights::ExponentialWeights,
+    },
+};
+
+/// The checkpoint that worker `index` trains in, under `--parallel_update averaged`.
+fn worker_checkpoint(index: usize) -> Option<String> {
+    Some(format!("{}worker-{}", RESERVED_CHECKPOINT_PREFIX, index))
+}
+
+/// How `merge_worker_maps` combines the workers' values of a field.
+#[derive(Clone, Copy, PartialEq)]
+enum MergeMode {
+    /// Moves the field by the mean of the workers' changes.
+    Mean,
+    /// Moves the field by the sum of the workers' changes.
+    Sum,
+    /// Takes the largest of the workers' values.
+    Max,
+}
+
+/// One pass over `examples` by `--num_workers` threads, each with its own connection and a
+/// disjoint slice of the examples.
+///
+/// With `--parallel_update hogwild`, the workers read and write the weights of the namespace
+/// directly, without locking, so an update can overwrite a concurrent one on the same feature.
+/// With `averaged`, each worker trains its own copy of the model, and the namespace is then moved
+/// by the mean of the workers' changes (the sum, for counts).
+pub fn train_in_parallel(
+    connection: &mut Connection,
+    options: &CommandLineOptions,
+    estimator: &PremiseEstimator,
+    class_weights: &ClassWeights,
+    examples: &[TrainingExample],
+) -> Result<LossTotals, Box<dyn Error>> {
+    let namespace = &options.scenario_name;
+    match options.parallel_update.as_str() {
+        "hogwild" => {
+            let checkpoints = vec![None; options.num_workers];
+            run_workers(options, estimator, class_weights, examples, &checkpoints)
+        }
+        "averaged" => {
+            let checkpoints: Vec<Option<String>> =
+                (0..options.num_workers).map(worker_checkpoint).collect();
+            let worker_namespaces: Vec<String> = checkpoints
+                .iter()
+                .map(|checkpoint| checkpoint_namespace(namespace, checkpoint))
+                .collect();
+            let result = run_averaged_workers(
+                connection,
+                options,
+                estimator,
+                class_weights,
+                examples,
+                &checkpoints,
+                &worker_namespaces,
+            );
+            // The worker copies are removed whether or not the workers succeeded.
+            for worker_namespace in &worker_namespaces {
+                clear_model_state(connection, worker_namespace)?;
+            }
+            result
+        }
+        name => Err(format!("Unknown parallel update type {}", name).into()),
+    }
+}
+
+fn run_averaged_workers(
+    connection: &mut Connection,
+    options: &CommandLineOptions,
+    estimator: &PremiseEstimator,
+    class_weights: &ClassWeights,
+    examples: &[TrainingExample],
+    checkpoints: &[Option<String>],
+    worker_namespaces: &[String],
+) -> Result<LossTotals, Box<dyn Error>> {
+    let namespace = &options.scenario_name;
+    for worker_namespace in worker_namespaces {
+        copy_model_state(connection, namespace, worker_namespace)?;
+    }
+    let totals = run_workers(options, estimator, class_weights, examples, checkpoints)?;
+    average_worker_models(connection, namespace, worker_namespaces)?;
+    Ok(totals)
+}
+
+fn run_workers(
+    options: &CommandLineOptions,
+    estimator: &PremiseEstimator,
+    class_weights: &ClassWeights,
+    examples: &[TrainingExample],
+    checkpoints: &[Option<String>],
+) -> Result<LossTotals, Box<dyn Error>> {
+    let chunk_size = ((examples.len() + checkpoints.len() - 1) / checkpoints.len()).max(1);
+    // `Box<dyn Error>` can't cross threads, so the workers report errors as strings.
+    let results: Vec<Result<LossTotals, String>> = thread::scope(|scope| {
+        let handles: Vec<_> = examples
+            .chunks(chunk_size)
+            .zip(checkpoints)
+            .map(|(slice, checkpoint)| {
+                scope.spawn(move || {
+                    run_worker(options, estimator, class_weights, slice, checkpoint)
+                        .map_err(|e| e.to_string())
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|_| Err("Training worker panicked.".to_string()))
+            })
+            .collect()
+    });
+    let mut totals = LossTotals::default();
+    for result in results {
+        totals.add(&result?);
+    }
+    Ok(totals)
+}
+
+fn run_worker(
+    options: &CommandLineOptions,
+    estimator: &PremiseEstimator,
+    class_weights: &ClassWeights,
+    examples: &[TrainingExample],
+    checkpoint: &Option<String>,
+) -> Result<LossTotals, Box<dyn Error>> {
+    let namespace = options.scenario_name.clone();
+    let manager = RedisManager::new()?;
+    let mut connection = manager.get_connection()?.into_inner();
+    let graph = InferenceGraph::new_mutable(namespace.clone())?;
+    let proposition_db = RedisBeliefTable::new_mutable(&mut connection, namespace.clone())?;
+    let model_namespace = checkpoint_namespace(&namespace, checkpoint);
+    let mut factor_model =
+        FactorModelFactory::new_mutable(&mut connection, model_namespace.clone(), options)?;
+    let inference_model = if options.expectation_maximization {
+        Some(InferenceModel::new_shared_in_model_namespace(
+            &mut connection,
+            namespace.clone(),
+            model_namespace,
+        )?)
+    } else {
+        None
+    };
+    trace!(
+        "run_worker - Training {:?} on {} examples",
+        checkpoint,
+        examples.len()
+    );
+    train_on_examples(
+        &mut connection,
+        &mut factor_model,
+        &proposition_db,
+        estimator,
+        class_weights,
+        &graph,
+        &inference_model,
+        &ConvergenceOptions::new(options),
+        examples,
+    )
+}
+
+fn read_numeric_map(
+    connection: &mut Connection,
+    namespace: &str,
+    key: &str,
+) -> Result<HashMap<String, f64>, Box<dyn Error>> {
+    let mut result = HashMap::new();
+    for (field, record) in map_get_all(connection, namespace, key)? {
+        result.insert(field, record.parse::<f64>()?);
+    }
+    Ok(result)
+}
+
+/// Combines each field of the map `key` in `namespace` with the workers' values of it, by `mode`.
+/// Fields that are missing count as 0, as they do for lazily initialized weights.
+fn merge_worker_maps(
+    connection: &mut Connection,
+    namespace: &str,
+    key: &str,
+    worker_namespaces: &[String],
+    mode: MergeMode,
+) -> Result<(), Box<dyn Error>> {
+    let base = read_numeric_map(connection, namespace, key)?;
+    let mut deltas: HashMap<String, f64> = HashMap::new();
+    let mut maxima: HashMap<String, f64> = HashMap::new();
+    for worker_namespace in worker_namespaces {
+        for (field, value) in read_numeric_map(connection, worker_namespace, key)? {
+            let start = base.get(&field).copied().unwrap_or(0f64);
+            *deltas.entry(field.clone()).or_insert(0f64) += value - start;
+            let maximum = maxima.entry(field).or_insert(value);
+            *maximum = maximum.max(value);
+        }
+    }
+    let scale = match mode {
+        MergeMode::Sum => 1f64,
+        _ => 1f64 / worker_namespaces.len() as f64,
+    };
+    for (field, delta) in deltas {
+        let start = base.get(&field).copied().unwrap_or(0f64);
+        let value = match mode {
+            MergeMode::Max => maxima[&field],
+            _ => start + scale * delta,
+        };
+        map_insert(connection, namespace, key, &field, &value.to_string())?;
+    }
+    Ok(())
+}
+
+fn average_worker_models(
+    connection: &mut Connection,
+    namespace: &str,
+    worker_namespaces: &[String],
+) -> Result<(), Box<dyn Error>> {
+    let mut averaged_keys = vec![
+        ExponentialWeights::WEIGHTS_KEY.to_string(),
+        NoisyOrParameters::PARAMETERS_KEY.to_string(),
+    ];
+    for worker_namespace in worker_namespaces {
+        for key in namespace_keys(connection, worker_namespace, "optimizer:*")? {
+            if !averaged_keys.contains(&key) {
+                averaged_keys.push(key);
+            }
+        }
+    }
+    for key in &averaged_keys {
+        // The moments are averaged, but averaging the step counts would undercount the steps
+        // taken, and with them the bias correction, so the count of the furthest worker is kept.
+        let mode = if key == ADAM_STEPS_KEY {
+            MergeMode::Max
+        } else {
+            MergeMode::Mean
+        };
+        merge_worker_maps(connection, namespace, key, worker_namespaces, mode)?;
+    }
+    // Each worker counted different examples, so the counts add up.
+    merge_worker_maps(
+        connection,
+        namespace,
+        MonolithicBayes::COUNTS_KEY,
+        worker_namespaces,
+        MergeMode::Sum,
+    )?;
+    for worker_namespace in worker_namespaces {
+        for interaction in set_members(connection, worker_namespace, INTERACTIONS_KEY)? {
+            set_add(connection, namespace, INTERACTIONS_KEY, &interaction)?;
+        }
+    }
+    Ok(())
+}
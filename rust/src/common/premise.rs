@@ -0,0 +1,114 @@
+use std::{collections::HashMap, error::Error};
+
+use redis::Connection;
+
+use crate::{
+    common::{graph::InferenceGraph, interface::BeliefTable, random::random_f64},
+    model::{choose::extract_backimplications_from_proposition, objects::Proposition},
+};
+
+/// How the probabilities of the terms of a conjunction are combined into the probability of the
+/// conjunction, when the terms are not 0/1.
+pub enum PremiseEstimator {
+    /// Treat the terms as independent: the expected value of the AND under the product distribution.
+    Product,
+    /// Draw a 0/1 value for each term, so that each visit trains on one hard sample.
+    Sampled,
+    /// Scale the product by the lift of each implication's premise, measured over the training
+    /// data, and clamp it to the Frechet bounds.
+    Correlated { lifts: HashMap<String, f64> },
+}
+
+struct LiftCounts {
+    count: f64,
+    sum_product: f64,
+    sum_terms: Vec<f64>,
+}
+
+impl PremiseEstimator {
+    pub fn new(
+        connection: &mut Connection,
+        estimator_name: &str,
+        proposition_db: &Box<dyn BeliefTable>,
+        graph: &InferenceGraph,
+        training_questions: &Vec<Proposition>,
+    ) -> Result<PremiseEstimator, Box<dyn Error>> {
+        match estimator_name {
+            "product" => Ok(PremiseEstimator::Product),
+            "sampled" => Ok(PremiseEstimator::Sampled),
+            "correlated" => {
+                let lifts =
+                    compute_premise_lifts(connection, proposition_db, graph, training_questions)?;
+                Ok(PremiseEstimator::Correlated { lifts })
+            }
+            name => Err(format!("Unknown PremiseEstimator type {}", name).into()),
+        }
+    }
+
+    pub fn estimate(&self, implication_key: &str, term_probabilities: &[f64]) -> f64 {
+        let product: f64 = term_probabilities.iter().product();
+        match self {
+            PremiseEstimator::Product => product,
+            PremiseEstimator::Sampled => {
+                let all_true = term_probabilities.iter().all(|p| random_f64() < *p);
+                if all_true {
+                    1f64
+                } else {
+                    0f64
+                }
+            }
+            PremiseEstimator::Correlated { lifts } => {
+                let lift = lifts.get(implication_key).copied().unwrap_or(1f64);
+                let n = term_probabilities.len() as f64;
+                let lower = (term_probabilities.iter().sum::<f64>() - (n - 1f64)).max(0f64);
+                let upper = term_probabilities.iter().cloned().fold(1f64, f64::min);
+                (product * lift).clamp(lower, upper)
+            }
+        }
+    }
+}
+
+/// For each implication, E[product of terms] / product of E[term], over the training groundings.
+fn compute_premise_lifts(
+    connection: &mut Connection,
+    proposition_db: &Box<dyn BeliefTable>,
+    graph: &InferenceGraph,
+    training_questions: &Vec<Proposition>,
+) -> Result<HashMap<String, f64>, Box<dyn Error>> {
+    let mut counts: HashMap<String, LiftCounts> = HashMap::new();
+    for conclusion in training_questions {
+        let factors = extract_backimplications_from_proposition(connection, graph, conclusion)?;
+        for factor in &factors {
+            let mut parts = vec![];
+            for term in &factor.premise.terms {
+                if let Some(part) = proposition_db.get_proposition_probability(connection, term)? {
+                    parts.push(part);
+                }
+            }
+            if parts.len() != factor.premise.terms.len() {
+                continue;
+            }
+            let entry = counts
+                .entry(factor.inference.unique_key())
+                .or_insert_with(|| LiftCounts {
+                    count: 0f64,
+                    sum_product: 0f64,
+                    sum_terms: vec![0f64; parts.len()],
+                });
+            entry.count += 1f64;
+            entry.sum_product += parts.iter().product::<f64>();
+            for (index, part) in parts.iter().enumerate() {
+                entry.sum_terms[index] += part;
+            }
+        }
+    }
+    let mut lifts = HashMap::new();
+    for (key, entry) in &counts {
+        let independent: f64 = entry.sum_terms.iter().map(|s| s / entry.count).product();
+        let joint = entry.sum_product / entry.count;
+        let lift = if independent > 0f64 { joint / independent } else { 1f64 };
+        trace!("premise lift {} {}", key, lift);
+        lifts.insert(key.clone(), lift);
+    }
+    Ok(lifts)
+}
@@ -0,0 +1,120 @@
+use std::{collections::HashMap, error::Error};
+
+use redis::Connection;
+
+use crate::{
+    common::{
+        graph::InferenceGraph,
+        interface::BeliefTable,
+        redis::{map_get, map_insert},
+    },
+    model::{
+        choose::{extract_backimplications_from_proposition, is_root_backimplications},
+        objects::{existence_predicate_name, Proposition},
+    },
+};
+
+/// Laplace smoothing, so that a relation seen only true or only false does not get a prior of
+/// exactly 1 or 0.
+const PRIOR_PSEUDO_COUNT: f64 = 1f64;
+
+/// The key for the prior of a relation, over all domains.
+fn relation_prior_key(proposition: &Proposition) -> String {
+    proposition.predicate.relation.relation_name.clone()
+}
+
+/// The key for the prior of a relation, restricted to the domains of its arguments.
+fn domain_prior_key(proposition: &Proposition) -> String {
+    proposition.predicate.domain_signature()
+}
+
+/// The probability that a root proposition, one with no implications into it but the existence
+/// factor, is true before any evidence is seen. Learned from the stored training facts.
+pub struct RootPriors {
+    namespace: String,
+}
+
+struct PriorCounts {
+    count: f64,
+    sum: f64,
+}
+
+impl RootPriors {
+    pub const PRIORS_KEY: &'static str = "root_priors";
+
+    pub fn new(namespace: String) -> Result<RootPriors, Box<dyn Error>> {
+        Ok(RootPriors { namespace })
+    }
+
+    /// The prior for the relation and domains of `proposition` if one was learned, else the prior
+    /// for the relation, else None. `exists` is always true.
+    pub fn get_prior(
+        &self,
+        connection: &mut Connection,
+        proposition: &Proposition,
+    ) -> Result<Option<f64>, Box<dyn Error>> {
+        if proposition.predicate.relation.relation_name == existence_predicate_name() {
+            return Ok(Some(1f64));
+        }
+        for key in [
+            domain_prior_key(proposition),
+            relation_prior_key(proposition),
+        ] {
+            if let Some(record) = map_get(connection, &self.namespace, Self::PRIORS_KEY, &key)? {
+                return Ok(Some(record.parse::<f64>()?));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Averages the stored probability of each training proposition that is a root by
+    /// `is_root_backimplications`, per relation and per relation and domains.
+    pub fn learn_priors(
+        &mut self,
+        connection: &mut Connection,
+        proposition_db: &Box<dyn BeliefTable>,
+        graph: &InferenceGraph,
+        training_questions: &Vec<Proposition>,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut counts: HashMap<String, PriorCounts> = HashMap::new();
+        for proposition in training_questions {
+            if proposition.predicate.relation.relation_name == existence_predicate_name() {
+                continue;
+            }
+            let factors =
+                extract_backimplications_from_proposition(connection, graph, proposition)?;
+            if !is_root_backimplications(&factors) {
+                continue;
+            }
+            let probability =
+                match proposition_db.get_proposition_probability(connection, proposition)? {
+                    Some(probability) => probability,
+                    None => continue,
+                };
+            for key in [
+                domain_prior_key(proposition),
+                relation_prior_key(proposition),
+            ] {
+                let entry = counts.entry(key).or_insert(PriorCounts {
+                    count: 0f64,
+                    sum: 0f64,
+                });
+                entry.count += 1f64;
+                entry.sum += probability;
+            }
+        }
+        for (key, entry) in &counts {
+            let prior =
+                (entry.sum + PRIOR_PSEUDO_COUNT) / (entry.count + 2f64 * PRIOR_PSEUDO_COUNT);
+            trace!("root prior {} {}", key, prior);
+            map_insert(
+                connection,
+                &self.namespace,
+                Self::PRIORS_KEY,
+                key,
+                &prior.to_string(),
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -11,7 +11,7 @@ use crate::{
     },
 };
 use redis::{Commands, Connection};
-use std::{cell::RefCell, collections::HashMap, error::Error, io::Empty, rc::Rc, sync::{Arc, Mutex}};
+use std::{cell::RefCell, collections::{HashMap, HashSet}, error::Error, io::Empty, rc::Rc, sync::{Arc, Mutex}};
 
 use super::{
     graph::InferenceGraph,
@@ -21,16 +21,50 @@ use super::{
 
 pub struct RedisBeliefTable {
     namespace: String,
+    /// The closed-world relations of the namespace, as registered when the table was created.
+    closed_world_relations: HashSet<String>,
 }
 
 impl RedisBeliefTable {
-    pub fn new_mutable(namespace: String) -> Result<Box<dyn BeliefTable>, Box<dyn Error>> {
-        Ok(Box::new(RedisBeliefTable { namespace }))
+    pub fn new_mutable(
+        connection: &mut Connection,
+        namespace: String,
+    ) -> Result<Box<dyn BeliefTable>, Box<dyn Error>> {
+        Ok(Box::new(Self::new(connection, namespace)?))
+    }
+    pub fn new_shared(
+        connection: &mut Connection,
+        namespace: String,
+    ) -> Result<Rc<dyn BeliefTable>, Box<dyn Error>> {
+        Ok(Rc::new(Self::new(connection, namespace)?))
     }
-    pub fn new_shared(namespace: String) -> Result<Rc<dyn BeliefTable>, Box<dyn Error>> {
-        Ok(Rc::new(RedisBeliefTable { namespace }))
+    fn new(connection: &mut Connection, namespace: String) -> Result<Self, Box<dyn Error>> {
+        let graph = InferenceGraph::new_mutable(namespace.clone())?;
+        let closed_world_relations = graph
+            .get_closed_world_relations(connection)?
+            .into_iter()
+            .collect();
+        Ok(RedisBeliefTable {
+            namespace,
+            closed_world_relations,
+        })
     }
     pub const PROBABILITIES_KEY: &'static str = "probabilities";
+
+    /// Whether `proposition` has a stored probability, as opposed to a closed-world default.
+    pub fn has_stored_probability(
+        connection: &mut Connection,
+        namespace: &str,
+        proposition: &Proposition,
+    ) -> Result<bool, Box<dyn Error>> {
+        let record = map_get(
+            connection,
+            namespace,
+            Self::PROBABILITIES_KEY,
+            &proposition.predicate.hash_string(),
+        )?;
+        Ok(record.is_some())
+    }
 }
 
 impl BeliefTable for RedisBeliefTable {
@@ -44,13 +78,22 @@ impl BeliefTable for RedisBeliefTable {
             return Ok(Some(1f64));
         }
         let hash_string = proposition.predicate.hash_string();
-        let probability_record = map_get(
-             connection,
+        let probability_record = match map_get(
+            connection,
             &self.namespace,
             Self::PROBABILITIES_KEY,
             &hash_string,
-        )?
-        .expect("should be there");
+        )? {
+            Some(record) => record,
+            None => {
+                // Groundings of a closed-world relation that were never stored are false.
+                let relation_name = &proposition.predicate.relation.relation_name;
+                if self.closed_world_relations.contains(relation_name) {
+                    return Ok(Some(0f64));
+                }
+                return Ok(None);
+            }
+        };
         let probability = probability_record
             .parse::<f64>()
             .map_err(|e| Box::new(e) as Box<dyn Error>)?;
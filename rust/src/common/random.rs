@@ -0,0 +1,71 @@
+use once_cell::sync::Lazy;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::sync::Mutex;
+
+/// The process-wide random source, re-seeded by `--seed` so that runs can be reproduced.
+static GLOBAL_RNG: Lazy<Mutex<StdRng>> = Lazy::new(|| Mutex::new(StdRng::from_entropy()));
+static GLOBAL_SEED: Lazy<Mutex<Option<u64>>> = Lazy::new(|| Mutex::new(None));
+
+pub fn set_global_seed(seed: u64) {
+    *GLOBAL_RNG.lock().unwrap() = StdRng::seed_from_u64(seed);
+    *GLOBAL_SEED.lock().unwrap() = Some(seed);
+}
+
+pub fn global_seed() -> Option<u64> {
+    *GLOBAL_SEED.lock().unwrap()
+}
+
+/// A uniform sample from [0, 1) out of the global stream.
+pub fn random_f64() -> f64 {
+    GLOBAL_RNG.lock().unwrap().gen::<f64>()
+}
+
+/// The 64-bit FNV-1a hash of `bytes`, continuing from `hash`. Unlike `DefaultHasher`, whose
+/// algorithm may change between Rust releases, this is fixed, so seeded runs are the same on
+/// every toolchain.
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+
+/// A generator that depends only on the global seed and `key`, not on how many numbers were drawn
+/// before, so that results don't depend on the order in which redis returns set members.
+pub fn keyed_rng(key: &str) -> StdRng {
+    match global_seed() {
+        Some(seed) => {
+            let hash = fnv1a(FNV_OFFSET_BASIS, &seed.to_le_bytes());
+            StdRng::seed_from_u64(fnv1a(hash, key.as_bytes()))
+        }
+        None => StdRng::from_entropy(),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Every test that sets the global seed uses this one, since tests run in parallel.
+    pub const TEST_SEED: u64 = 17;
+
+    fn draw(key: &str) -> Vec<u64> {
+        let mut rng = keyed_rng(key);
+        (0..8).map(|_| rng.gen::<u64>()).collect()
+    }
+
+    #[test]
+    fn keyed_rng_repeats_for_the_same_seed_and_key() {
+        set_global_seed(TEST_SEED);
+        assert_eq!(draw("+>+ feature"), draw("+>+ feature"));
+    }
+
+    #[test]
+    fn keyed_rng_differs_between_keys() {
+        set_global_seed(TEST_SEED);
+        assert_ne!(draw("+>+ feature"), draw("->+ feature"));
+    }
+}
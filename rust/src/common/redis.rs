@@ -1,6 +1,7 @@
 use redis::Commands;
 use redis::Connection;
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::error::Error;
 use std::sync::Arc;
 use std::sync::Mutex;
@@ -94,12 +95,111 @@ pub fn map_get(
     Ok(value)
 }
 
+/// Every field of the map `key`, with its value.
+pub fn map_get_all(
+    conn: &mut Connection,
+    namespace: &str,
+    key: &str,
+) -> Result<HashMap<String, String>, Box<dyn Error>> {
+    let nskey = &namespace_qualified_key(namespace, key);
+    let values: HashMap<String, String> = conn.hgetall(nskey)?;
+    Ok(values)
+}
+
+pub fn map_delete(
+    conn: &mut Connection,
+    namespace: &str,
+    key: &str,
+    field: &str,
+) -> Result<(), Box<dyn Error>> {
+    let nskey = &namespace_qualified_key(namespace, key);
+    let _removed: i64 = conn.hdel(nskey, field)?;
+    Ok(())
+}
+
+pub fn map_clear(conn: &mut Connection, namespace: &str, key: &str) -> Result<(), Box<dyn Error>> {
+    let nskey = &namespace_qualified_key(namespace, key);
+    let _removed: i64 = conn.del(nskey)?;
+    Ok(())
+}
+
+/// The fully qualified keys that match `pattern`, found with SCAN, which unlike KEYS does not
+/// block the server while it walks the whole keyspace.
+fn scan_keys(conn: &mut Connection, pattern: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let keys: Vec<String> = conn.scan_match(pattern)?.collect();
+    Ok(keys)
+}
+
+/// Deletes every key in `namespace`.
+pub fn namespace_clear(conn: &mut Connection, namespace: &str) -> Result<(), Box<dyn Error>> {
+    let pattern = namespace_qualified_key(namespace, "*");
+    let keys = scan_keys(conn, &pattern)?;
+    if !keys.is_empty() {
+        let _removed: i64 = conn.del(keys)?;
+    }
+    Ok(())
+}
+
+/// The keys in `namespace` that match `pattern`, without the namespace prefix.
+pub fn namespace_keys(conn: &mut Connection, namespace: &str, pattern: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let prefix = namespace_qualified_key(namespace, "");
+    let keys = scan_keys(conn, &namespace_qualified_key(namespace, pattern))?;
+    // SCAN can return a key more than once.
+    let mut result: Vec<String> = keys
+        .into_iter()
+        .map(|key| key[prefix.len()..].to_string())
+        .collect();
+    result.sort();
+    result.dedup();
+    Ok(result)
+}
+
+pub fn key_delete(conn: &mut Connection, namespace: &str, key: &str) -> Result<(), Box<dyn Error>> {
+    let nskey = &namespace_qualified_key(namespace, key);
+    let _removed: i64 = conn.del(nskey)?;
+    Ok(())
+}
+
+pub fn key_exists(conn: &mut Connection, namespace: &str, key: &str) -> Result<bool, Box<dyn Error>> {
+    let nskey = &namespace_qualified_key(namespace, key);
+    let exists: bool = conn.exists(nskey)?;
+    Ok(exists)
+}
+
+/// Copies `key`, of any type, from one namespace to another, replacing it. Does nothing if the
+/// key does not exist.
+pub fn key_copy(
+    conn: &mut Connection,
+    from_namespace: &str,
+    to_namespace: &str,
+    key: &str,
+) -> Result<(), Box<dyn Error>> {
+    let from_key = namespace_qualified_key(from_namespace, key);
+    let to_key = namespace_qualified_key(to_namespace, key);
+    let dump: Option<Vec<u8>> = redis::cmd("DUMP").arg(&from_key).query(conn)?;
+    if let Some(dump) = dump {
+        redis::cmd("RESTORE")
+            .arg(&to_key)
+            .arg(0)
+            .arg(dump)
+            .arg("REPLACE")
+            .query::<()>(conn)?;
+    }
+    Ok(())
+}
+
 pub fn set_add(conn: &mut Connection, namespace: &str, key: &str, member: &str) -> Result<bool, Box<dyn Error>> {
     let nskey = &namespace_qualified_key(namespace, key);
     let added: bool = conn.sadd(nskey, member)?;
     Ok(added)
 }
 
+pub fn set_remove(conn: &mut Connection, namespace: &str, key: &str, member: &str) -> Result<bool, Box<dyn Error>> {
+    let nskey = &namespace_qualified_key(namespace, key);
+    let removed: bool = conn.srem(nskey, member)?;
+    Ok(removed)
+}
+
 pub fn set_members(conn: &mut Connection, namespace: &str, key: &str) -> Result<Vec<String>, Box<dyn Error>> {
     let nskey = &namespace_qualified_key(namespace, key);
     let members: Vec<String> = conn.smembers(nskey)?;
@@ -118,11 +218,24 @@ pub fn seq_push(conn: &mut Connection, namespace: &str, key: &str, value: &str)
     Ok(length)
 }
 
+pub fn seq_clear(conn: &mut Connection, namespace: &str, key: &str) -> Result<(), Box<dyn Error>> {
+    let nskey = &namespace_qualified_key(namespace, key);
+    let _removed: i64 = conn.del(nskey)?;
+    Ok(())
+}
+
 // pub fn seq_pop(conn: &mut Connection, key: &str) -> Result<Option<String>, Box<dyn Error>> {
 //     let value: Option<String> = conn.lpop(key, None)?;
 //     Ok(value)
 // }
 
+/// The elements of the sequence from index `start` to the end.
+pub fn seq_get_from(conn: &mut Connection, namespace: &str, key: &str, start: usize) -> Result<Vec<String>, Box<dyn Error>> {
+    let nskey = &namespace_qualified_key(namespace, key);
+    let elements: Vec<String> = conn.lrange(nskey, start as isize, -1)?;
+    Ok(elements)
+}
+
 pub fn seq_get_all(conn: &mut Connection, namespace: &str, key: &str) -> Result<Vec<String>, Box<dyn Error>> {
     let nskey = &namespace_qualified_key(namespace, key);
     let elements: Vec<String> = conn.lrange(nskey, 0, -1)?;
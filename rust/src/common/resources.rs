@@ -1,8 +1,14 @@
 use std::{error::Error, sync::{Arc, Mutex}};
+use crate::inference::inference::ConvergenceOptions;
+
 use super::{redis::RedisManager, setup::CommandLineOptions};
 
 pub struct ResourceContext {
     pub connection: Arc<Mutex<redis::Connection>>,
+    /// The checkpoint that inference and the explorer read the model from, if not the live one.
+    pub checkpoint: Option<String>,
+    /// When belief propagation stops, for the plot and explorer pages.
+    pub convergence: ConvergenceOptions,
 }
 
 impl ResourceContext {
@@ -11,6 +17,8 @@ impl ResourceContext {
         let connection = manager.get_arc_mutex_guarded_connection()?;
         Ok(ResourceContext {
             connection,
+            checkpoint: options.checkpoint.clone(),
+            convergence: ConvergenceOptions::new(options),
         })
     }
 }
\ No newline at end of file
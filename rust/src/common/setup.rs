@@ -1,3 +1,4 @@
+use crate::common::random::set_global_seed;
 use crate::common::resources::ResourceContext;
 use clap::{App, Arg};
 use env_logger::{Builder, Env};
@@ -14,6 +15,52 @@ pub struct CommandLineOptions {
     pub print_training_loss: bool,
     pub test_example: Option<u32>,
     pub marginal_output_file: Option<String>,
+    pub optimizer: String,
+    pub learning_rate: f64,
+    pub resume_training: bool,
+    pub l1_penalty: f64,
+    pub l2_penalty: f64,
+    pub gradient_clip: Option<f64>,
+    pub weight_clip: Option<f64>,
+    pub num_epochs: usize,
+    pub evaluation_output_file: Option<String>,
+    pub seed: Option<u64>,
+    pub expectation_maximization: bool,
+    pub premise_estimator: String,
+    pub factor_model: String,
+    pub interaction_order: usize,
+    pub domain_bias: bool,
+    pub compare_baseline: bool,
+    pub max_premises: usize,
+    pub structure_score: String,
+    pub min_rule_gain: f64,
+    pub max_rules_per_relation: usize,
+    pub entities_file: Option<String>,
+    pub facts_file: Option<String>,
+    pub incremental: bool,
+    pub checkpoint: Option<String>,
+    pub checkpoint_every_epoch: bool,
+    pub save_checkpoint: Option<String>,
+    pub restore_checkpoint: Option<String>,
+    pub num_workers: usize,
+    pub parallel_update: String,
+    pub freeze_implication: Option<String>,
+    pub frozen_probability: Option<f64>,
+    pub frozen_weights: Option<Vec<f64>>,
+    pub unfreeze_implication: Option<String>,
+    pub warm_start_namespace: Option<String>,
+    pub warm_start_checkpoint: Option<String>,
+    pub class_balance: String,
+    pub closed_world_relations: Vec<String>,
+    pub open_world_relations: Vec<String>,
+    pub negative_ratio: f64,
+    pub bp_tolerance: f64,
+    pub max_bp_sweeps: usize,
+    pub bp_damping: f64,
+    pub bp_schedule: String,
+    pub gibbs_samples: usize,
+    pub gibbs_burn_in: usize,
+    pub gibbs_thinning: usize,
 }
 
 fn check_file_does_not_exist(file_name: &str) {
@@ -85,6 +132,345 @@ pub fn parse_configuration_options() -> CommandLineOptions {
                 .help("Sets the file name for marginal output (optional)")
                 .takes_value(true), // This argument is optional and takes a string value
         )
+        .arg(
+            Arg::with_name("optimizer")
+                .long("optimizer")
+                .value_name("STRING")
+                .help("Sets the optimizer used in training: sgd, momentum, adagrad or adam")
+                .takes_value(true)
+                .default_value("sgd"),
+        )
+        .arg(
+            Arg::with_name("learning_rate")
+                .long("learning_rate")
+                .value_name("NUMBER")
+                .help("Sets the learning rate of the optimizer")
+                .takes_value(true)
+                .default_value("0.05"),
+        )
+        .arg(
+            Arg::with_name("resume_training")
+                .long("resume_training")
+                .help("Keeps existing weights and optimizer state instead of re-initializing them")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("l1_penalty")
+                .long("l1_penalty")
+                .value_name("NUMBER")
+                .help("Sets the L1 penalty on the weights")
+                .takes_value(true)
+                .default_value("0"),
+        )
+        .arg(
+            Arg::with_name("l2_penalty")
+                .long("l2_penalty")
+                .value_name("NUMBER")
+                .help("Sets the L2 penalty on the weights")
+                .takes_value(true)
+                .default_value("0"),
+        )
+        .arg(
+            Arg::with_name("gradient_clip")
+                .long("gradient_clip")
+                .value_name("NUMBER")
+                .help("Clips each gradient component to this absolute value (optional)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("weight_clip")
+                .long("weight_clip")
+                .value_name("NUMBER")
+                .help("Clips each weight to this absolute value (optional)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("num_epochs")
+                .long("num_epochs")
+                .value_name("NUMBER")
+                .help("Sets the number of passes over the training queue")
+                .takes_value(true)
+                .default_value("1"),
+        )
+        .arg(
+            Arg::with_name("evaluation_output_file")
+                .long("evaluation_output_file")
+                .value_name("FILE")
+                .help("Sets the file name for the JSON evaluation report (optional)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("seed")
+                .long("seed")
+                .value_name("NUMBER")
+                .help("Seeds weight initialization and scenario generation (optional)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("expectation_maximization")
+                .long("expectation_maximization")
+                .help("Trains on examples with unobserved premises, using inferred expected values")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("premise_estimator")
+                .long("premise_estimator")
+                .value_name("STRING")
+                .help("Sets how fractional premise probabilities combine in training: product, sampled or correlated")
+                .takes_value(true)
+                .default_value("product"),
+        )
+        .arg(
+            Arg::with_name("factor_model")
+                .long("factor_model")
+                .value_name("STRING")
+                .help("Sets the factor model to train: exponential, interaction, noisy_or or monolithic")
+                .takes_value(true)
+                .default_value("exponential"),
+        )
+        .arg(
+            Arg::with_name("interaction_order")
+                .long("interaction_order")
+                .value_name("NUMBER")
+                .help("Sets the largest number of backimplications in one interaction feature")
+                .takes_value(true)
+                .default_value("2"),
+        )
+        .arg(
+            Arg::with_name("domain_bias")
+                .long("domain_bias")
+                .help("Adds a bias feature per conclusion domain signature, as well as per relation")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("compare_baseline")
+                .long("compare_baseline")
+                .help("Also evaluates a count-based baseline and reports the gain over it")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("max_premises")
+                .long("max_premises")
+                .value_name("NUMBER")
+                .help("Sets the largest number of terms in a rule proposed by structure learning")
+                .takes_value(true)
+                .default_value("2"),
+        )
+        .arg(
+            Arg::with_name("structure_score")
+                .long("structure_score")
+                .value_name("STRING")
+                .help("Sets how structure learning scores rules: heldout or bic")
+                .takes_value(true)
+                .default_value("heldout"),
+        )
+        .arg(
+            Arg::with_name("min_rule_gain")
+                .long("min_rule_gain")
+                .value_name("NUMBER")
+                .help("Sets the per-example gain over the base rate that a learned rule needs")
+                .takes_value(true)
+                .default_value("0.01"),
+        )
+        .arg(
+            Arg::with_name("max_rules_per_relation")
+                .long("max_rules_per_relation")
+                .value_name("NUMBER")
+                .help("Sets the most rules that structure learning stores for one conclusion")
+                .takes_value(true)
+                .default_value("3"),
+        )
+        .arg(
+            Arg::with_name("entities_file")
+                .long("entities_file")
+                .value_name("FILE")
+                .help("Sets the CSV or JSON Lines file of entities to import (optional)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("facts_file")
+                .long("facts_file")
+                .value_name("FILE")
+                .help("Sets the CSV or JSON Lines file of facts to import (optional)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("incremental")
+                .long("incremental")
+                .help("Continues from the stored weights and trains only on questions queued since the last run")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("checkpoint")
+                .long("checkpoint")
+                .value_name("STRING")
+                .help("Sets the checkpoint that inference, evaluation and the explorer read (optional)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("checkpoint_every_epoch")
+                .long("checkpoint_every_epoch")
+                .help("Saves a checkpoint named epoch-<n> at the end of each training epoch")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("save_checkpoint")
+                .long("save_checkpoint")
+                .value_name("STRING")
+                .help("Saves the current model under this checkpoint name (optional)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("restore_checkpoint")
+                .long("restore_checkpoint")
+                .value_name("STRING")
+                .help("Replaces the current model with this checkpoint (optional)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("num_workers")
+                .long("num_workers")
+                .value_name("NUMBER")
+                .help("Sets the number of training threads, each with its own connection")
+                .takes_value(true)
+                .default_value("1"),
+        )
+        .arg(
+            Arg::with_name("parallel_update")
+                .long("parallel_update")
+                .value_name("STRING")
+                .help("Sets how parallel workers update the weights: hogwild or averaged")
+                .takes_value(true)
+                .default_value("hogwild"),
+        )
+        .arg(
+            Arg::with_name("freeze_implication")
+                .long("freeze_implication")
+                .value_name("STRING")
+                .help("Freezes the implication with this unique key, at --frozen_probability or --frozen_weights (optional)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("frozen_probability")
+                .long("frozen_probability")
+                .value_name("NUMBER")
+                .help("Sets the noisy-or activation of the frozen implication (optional)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("frozen_weights")
+                .long("frozen_weights")
+                .value_name("LIST")
+                .help("Sets the four weights of the frozen implication, comma-separated: +>-, ->-, +>+, ->+ (optional)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("unfreeze_implication")
+                .long("unfreeze_implication")
+                .value_name("STRING")
+                .help("Lets training update the implication with this unique key again (optional)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("warm_start_namespace")
+                .long("warm_start_namespace")
+                .value_name("STRING")
+                .help("Initializes weights from the matching features of this namespace (optional)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("warm_start_checkpoint")
+                .long("warm_start_checkpoint")
+                .value_name("STRING")
+                .help("Reads the warm start weights from this checkpoint of --warm_start_namespace (optional)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("class_balance")
+                .long("class_balance")
+                .value_name("STRING")
+                .help("Sets how training examples are reweighted by class: none or relation")
+                .takes_value(true)
+                .default_value("none"),
+        )
+        .arg(
+            Arg::with_name("closed_world_relations")
+                .long("closed_world_relations")
+                .value_name("LIST")
+                .help("Marks these relations, comma-separated, as closed-world: facts that are not stored are false (optional)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("open_world_relations")
+                .long("open_world_relations")
+                .value_name("LIST")
+                .help("Makes these relations, comma-separated, open-world again after --closed_world_relations (optional)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("negative_ratio")
+                .long("negative_ratio")
+                .value_name("NUMBER")
+                .help("Sets the number of negative groundings sampled per training example of a closed-world relation")
+                .takes_value(true)
+                .default_value("1"),
+        )
+        .arg(
+            Arg::with_name("bp_tolerance")
+                .long("bp_tolerance")
+                .value_name("NUMBER")
+                .help("Stops belief propagation once no message changes by more than this in a sweep")
+                .takes_value(true)
+                .default_value("0.000001"),
+        )
+        .arg(
+            Arg::with_name("max_bp_sweeps")
+                .long("max_bp_sweeps")
+                .value_name("NUMBER")
+                .help("Sets the largest number of belief propagation sweeps")
+                .takes_value(true)
+                .default_value("50"),
+        )
+        .arg(
+            Arg::with_name("bp_damping")
+                .long("bp_damping")
+                .value_name("NUMBER")
+                .help("Sets the weight of the old message when belief propagation updates a message, in [0, 1)")
+                .takes_value(true)
+                .default_value("0"),
+        )
+        .arg(
+            Arg::with_name("bp_schedule")
+                .long("bp_schedule")
+                .value_name("TYPE")
+                .help("Sets the belief propagation schedule: sweep or residual")
+                .takes_value(true)
+                .default_value("sweep"),
+        )
+        .arg(
+            Arg::with_name("gibbs_samples")
+                .long("gibbs_samples")
+                .value_name("NUMBER")
+                .help("Sets the number of samples that Gibbs sampling keeps")
+                .takes_value(true)
+                .default_value("1000"),
+        )
+        .arg(
+            Arg::with_name("gibbs_burn_in")
+                .long("gibbs_burn_in")
+                .value_name("NUMBER")
+                .help("Sets the number of Gibbs sweeps discarded before the first sample")
+                .takes_value(true)
+                .default_value("100"),
+        )
+        .arg(
+            Arg::with_name("gibbs_thinning")
+                .long("gibbs_thinning")
+                .value_name("NUMBER")
+                .help("Sets the number of Gibbs sweeps per sample kept")
+                .takes_value(true)
+                .default_value("1"),
+        )
         .get_matches();
     let entities_per_domain: i32 = matches
         .value_of("entities_per_domain")
@@ -97,11 +483,174 @@ pub fn parse_configuration_options() -> CommandLineOptions {
             .expect("test_example needs to be a positive integer or omitted")
     });
     let marginal_output_file = matches.value_of("marginal_output_file").map(String::from);
+    let evaluation_output_file = matches.value_of("evaluation_output_file").map(String::from);
+    let entities_file = matches.value_of("entities_file").map(String::from);
+    let facts_file = matches.value_of("facts_file").map(String::from);
+    let checkpoint = matches.value_of("checkpoint").map(String::from);
+    let checkpoint_every_epoch = matches.is_present("checkpoint_every_epoch");
+    let save_checkpoint = matches.value_of("save_checkpoint").map(String::from);
+    let restore_checkpoint = matches.value_of("restore_checkpoint").map(String::from);
+    let freeze_implication = matches.value_of("freeze_implication").map(String::from);
+    let unfreeze_implication = matches.value_of("unfreeze_implication").map(String::from);
+    let frozen_probability: Option<f64> = matches.value_of("frozen_probability").map(|v| {
+        v.parse()
+            .expect("frozen_probability needs to be a number or omitted")
+    });
+    let frozen_weights: Option<Vec<f64>> = matches.value_of("frozen_weights").map(|v| {
+        v.split(',')
+            .map(|w| {
+                w.trim()
+                    .parse()
+                    .expect("frozen_weights needs to be a comma-separated list of numbers")
+            })
+            .collect()
+    });
+    let warm_start_namespace = matches.value_of("warm_start_namespace").map(String::from);
+    let warm_start_checkpoint = matches.value_of("warm_start_checkpoint").map(String::from);
+    let seed: Option<u64> = matches.value_of("seed").map(|v| {
+        v.parse()
+            .expect("seed needs to be a positive integer or omitted")
+    });
+    if let Some(seed) = seed {
+        set_global_seed(seed);
+    }
     let scenario_name: String = matches
         .value_of("scenario_name")
         .expect("scenario_name is required") // As it's required, unwrap directly
         .to_string();
     let test_scenario = matches.value_of("test_scenario").map(String::from);
+    let optimizer = matches
+        .value_of("optimizer")
+        .unwrap() // safe because we have a default value
+        .to_string();
+    let learning_rate: f64 = matches
+        .value_of("learning_rate")
+        .unwrap() // safe because we have a default value
+        .parse()
+        .expect("learning_rate needs to be a number");
+    let resume_training = matches.is_present("resume_training");
+    let incremental = matches.is_present("incremental");
+    let expectation_maximization = matches.is_present("expectation_maximization");
+    let domain_bias = matches.is_present("domain_bias");
+    let compare_baseline = matches.is_present("compare_baseline");
+    let factor_model = matches
+        .value_of("factor_model")
+        .unwrap() // safe because we have a default value
+        .to_string();
+    let interaction_order: usize = matches
+        .value_of("interaction_order")
+        .unwrap() // safe because we have a default value
+        .parse()
+        .expect("interaction_order needs to be a positive integer");
+    let max_premises: usize = matches
+        .value_of("max_premises")
+        .unwrap() // safe because we have a default value
+        .parse()
+        .expect("max_premises needs to be a positive integer");
+    let structure_score = matches
+        .value_of("structure_score")
+        .unwrap() // safe because we have a default value
+        .to_string();
+    let min_rule_gain: f64 = matches
+        .value_of("min_rule_gain")
+        .unwrap() // safe because we have a default value
+        .parse()
+        .expect("min_rule_gain needs to be a number");
+    let max_rules_per_relation: usize = matches
+        .value_of("max_rules_per_relation")
+        .unwrap() // safe because we have a default value
+        .parse()
+        .expect("max_rules_per_relation needs to be a positive integer");
+    let num_workers: usize = matches
+        .value_of("num_workers")
+        .unwrap() // safe because we have a default value
+        .parse()
+        .expect("num_workers needs to be a positive integer");
+    let parallel_update = matches
+        .value_of("parallel_update")
+        .unwrap() // safe because we have a default value
+        .to_string();
+    let class_balance = matches
+        .value_of("class_balance")
+        .unwrap() // safe because we have a default value
+        .to_string();
+    let closed_world_relations: Vec<String> = matches
+        .value_of("closed_world_relations")
+        .map(|v| v.split(',').map(|name| name.trim().to_string()).collect())
+        .unwrap_or_default();
+    let open_world_relations: Vec<String> = matches
+        .value_of("open_world_relations")
+        .map(|v| v.split(',').map(|name| name.trim().to_string()).collect())
+        .unwrap_or_default();
+    let negative_ratio: f64 = matches
+        .value_of("negative_ratio")
+        .unwrap() // safe because we have a default value
+        .parse()
+        .expect("negative_ratio needs to be a number");
+    let bp_tolerance: f64 = matches
+        .value_of("bp_tolerance")
+        .unwrap() // safe because we have a default value
+        .parse()
+        .expect("bp_tolerance needs to be a number");
+    let max_bp_sweeps: usize = matches
+        .value_of("max_bp_sweeps")
+        .unwrap() // safe because we have a default value
+        .parse()
+        .expect("max_bp_sweeps needs to be an integer");
+    let bp_damping: f64 = matches
+        .value_of("bp_damping")
+        .unwrap() // safe because we have a default value
+        .parse()
+        .expect("bp_damping needs to be a number");
+    if !(0f64..1f64).contains(&bp_damping) {
+        panic!("bp_damping needs to be in [0, 1)");
+    }
+    let bp_schedule = matches
+        .value_of("bp_schedule")
+        .unwrap() // safe because we have a default value
+        .to_string();
+    let gibbs_samples: usize = matches
+        .value_of("gibbs_samples")
+        .unwrap() // safe because we have a default value
+        .parse()
+        .expect("gibbs_samples needs to be an integer");
+    let gibbs_burn_in: usize = matches
+        .value_of("gibbs_burn_in")
+        .unwrap() // safe because we have a default value
+        .parse()
+        .expect("gibbs_burn_in needs to be an integer");
+    let gibbs_thinning: usize = matches
+        .value_of("gibbs_thinning")
+        .unwrap() // safe because we have a default value
+        .parse()
+        .expect("gibbs_thinning needs to be an integer");
+    let premise_estimator = matches
+        .value_of("premise_estimator")
+        .unwrap() // safe because we have a default value
+        .to_string();
+    let l1_penalty: f64 = matches
+        .value_of("l1_penalty")
+        .unwrap() // safe because we have a default value
+        .parse()
+        .expect("l1_penalty needs to be a number");
+    let l2_penalty: f64 = matches
+        .value_of("l2_penalty")
+        .unwrap() // safe because we have a default value
+        .parse()
+        .expect("l2_penalty needs to be a number");
+    let gradient_clip: Option<f64> = matches.value_of("gradient_clip").map(|v| {
+        v.parse()
+            .expect("gradient_clip needs to be a number or omitted")
+    });
+    let num_epochs: usize = matches
+        .value_of("num_epochs")
+        .unwrap() // safe because we have a default value
+        .parse()
+        .expect("num_epochs needs to be a positive integer");
+    let weight_clip: Option<f64> = matches.value_of("weight_clip").map(|v| {
+        v.parse()
+            .expect("weight_clip needs to be a number or omitted")
+    });
 
     CommandLineOptions {
         scenario_name,
@@ -110,5 +659,51 @@ pub fn parse_configuration_options() -> CommandLineOptions {
         print_training_loss,
         test_example,
         marginal_output_file,
+        optimizer,
+        learning_rate,
+        resume_training,
+        l1_penalty,
+        l2_penalty,
+        gradient_clip,
+        weight_clip,
+        num_epochs,
+        evaluation_output_file,
+        seed,
+        expectation_maximization,
+        premise_estimator,
+        factor_model,
+        interaction_order,
+        domain_bias,
+        compare_baseline,
+        max_premises,
+        structure_score,
+        min_rule_gain,
+        max_rules_per_relation,
+        entities_file,
+        facts_file,
+        incremental,
+        checkpoint,
+        checkpoint_every_epoch,
+        save_checkpoint,
+        restore_checkpoint,
+        num_workers,
+        parallel_update,
+        freeze_implication,
+        frozen_probability,
+        frozen_weights,
+        unfreeze_implication,
+        warm_start_namespace,
+        warm_start_checkpoint,
+        class_balance,
+        closed_world_relations,
+        open_world_relations,
+        negative_ratio,
+        bp_tolerance,
+        max_bp_sweeps,
+        bp_damping,
+        bp_schedule,
+        gibbs_samples,
+        gibbs_burn_in,
+        gibbs_thinning,
     }
 }
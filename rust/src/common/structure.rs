@@ -0,0 +1,394 @@
+use std::{collections::HashMap, error::Error};
+
+use redis::Connection;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::{
+        graph::InferenceGraph,
+        interface::{BeliefTable, TrainStatistics},
+        model::{FactorContext, FactorModel},
+        proposition_db::RedisBeliefTable,
+        redis::namespace_clear,
+        setup::CommandLineOptions,
+        train::TrainingPlan,
+    },
+    inference::graph::PropositionFactor,
+    model::{
+        creators::{conjunction, implication, predicate, role, variable},
+        exponential::ExponentialModel,
+        objects::{
+            existence_predicate_name, Argument, ImplicationFactor, Predicate, Proposition,
+            PropositionGroup, Relation, RoleMap,
+        },
+        ops::{convert_to_proposition, extract_premise_role_map},
+        weights::{bias_features, implication_features},
+    },
+};
+
+/// Every fifth example is held out when scoring by held-out likelihood.
+const HELD_OUT_EVERY: usize = 5;
+/// Keeps the base rate away from 0 and 1.
+const BASE_RATE_PSEUDO_COUNT: f64 = 1f64;
+
+/// A relation together with the domain of each role, as seen in the stored facts.
+#[derive(Debug, Clone)]
+struct RelationSchema {
+    relation: Relation,
+    roles: Vec<(String, String)>,
+}
+
+impl RelationSchema {
+    fn from_proposition(proposition: &Proposition) -> RelationSchema {
+        let roles = proposition
+            .predicate
+            .roles
+            .iter()
+            .map(|labeled| {
+                let domain = match &labeled.argument {
+                    Argument::Constant(argument) => argument.domain.clone(),
+                    Argument::Variable(argument) => argument.domain.clone(),
+                };
+                (labeled.role_name.clone(), domain)
+            })
+            .collect();
+        RelationSchema {
+            relation: proposition.predicate.relation.clone(),
+            roles,
+        }
+    }
+
+    fn quantified_predicate(&self) -> Predicate {
+        let roles = self
+            .roles
+            .iter()
+            .map(|(role_name, domain)| role(role_name.clone(), variable(domain.clone())))
+            .collect();
+        predicate(self.relation.clone(), roles)
+    }
+}
+
+/// One candidate premise term: a relation, and the conclusion role that fills each of its roles.
+#[derive(Debug, Clone)]
+struct CandidateTerm {
+    schema: RelationSchema,
+    /// Conclusion role name to premise role name, as in `RoleMap`.
+    role_map: HashMap<String, String>,
+}
+
+/// Every injective assignment of a conclusion role of the same domain to each premise role.
+fn role_assignments(
+    premise: &RelationSchema,
+    conclusion: &RelationSchema,
+) -> Vec<HashMap<String, String>> {
+    let mut result = vec![];
+    fn run(
+        index: usize,
+        premise: &RelationSchema,
+        conclusion: &RelationSchema,
+        current: &mut HashMap<String, String>,
+        result: &mut Vec<HashMap<String, String>>,
+    ) {
+        if index == premise.roles.len() {
+            result.push(current.clone());
+            return;
+        }
+        let (premise_role, premise_domain) = &premise.roles[index];
+        for (conclusion_role, conclusion_domain) in &conclusion.roles {
+            if conclusion_domain != premise_domain || current.contains_key(conclusion_role) {
+                continue;
+            }
+            current.insert(conclusion_role.clone(), premise_role.clone());
+            run(index + 1, premise, conclusion, current, result);
+            current.remove(conclusion_role);
+        }
+    }
+    run(0, premise, conclusion, &mut HashMap::new(), &mut result);
+    result
+}
+
+fn candidate_terms(schemas: &[RelationSchema], conclusion: &RelationSchema) -> Vec<CandidateTerm> {
+    let conclusion_signature = conclusion.quantified_predicate().domain_signature();
+    let mut result = vec![];
+    for schema in schemas {
+        let is_conclusion =
+            schema.quantified_predicate().domain_signature() == conclusion_signature;
+        for role_map in role_assignments(schema, conclusion) {
+            // A conclusion does not imply itself.
+            let is_identity = role_map.iter().all(|(from, to)| from == to);
+            if is_conclusion && is_identity {
+                continue;
+            }
+            result.push(CandidateTerm {
+                schema: schema.clone(),
+                role_map,
+            });
+        }
+    }
+    result
+}
+
+/// Conjunctions of between 1 and `max_premises` distinct candidate terms.
+fn candidate_conjunctions(terms: &[CandidateTerm], max_premises: usize) -> Vec<Vec<usize>> {
+    let mut result = vec![];
+    fn run(
+        start: usize,
+        total: usize,
+        max_premises: usize,
+        current: &mut Vec<usize>,
+        result: &mut Vec<Vec<usize>>,
+    ) {
+        if !current.is_empty() {
+            result.push(current.clone());
+        }
+        if current.len() == max_premises {
+            return;
+        }
+        for index in start..total {
+            current.push(index);
+            run(index + 1, total, max_premises, current, result);
+            current.pop();
+        }
+    }
+    run(0, terms.len(), max_premises, &mut vec![], &mut result);
+    result
+}
+
+fn build_implication(
+    terms: &[CandidateTerm],
+    indices: &[usize],
+    conclusion: &RelationSchema,
+) -> ImplicationFactor {
+    let premises = indices
+        .iter()
+        .map(|&i| terms[i].schema.quantified_predicate())
+        .collect();
+    let role_maps = indices
+        .iter()
+        .map(|&i| RoleMap::new(terms[i].role_map.clone()))
+        .collect();
+    implication(
+        conjunction(premises),
+        conclusion.quantified_predicate(),
+        role_maps,
+    )
+}
+
+/// The factor for `conclusion` with `candidate` as its only backimplication, or None if some
+/// premise term has no stored probability.
+fn candidate_factor(
+    connection: &mut Connection,
+    proposition_db: &Box<dyn BeliefTable>,
+    candidate: &ImplicationFactor,
+    conclusion: &Proposition,
+) -> Result<Option<FactorContext>, Box<dyn Error>> {
+    let mut terms = vec![];
+    let mut probability = 1f64;
+    for (index, premise) in candidate.premise.terms.iter().enumerate() {
+        let mapping = extract_premise_role_map(conclusion, &candidate.role_maps.role_maps[index]);
+        let term = convert_to_proposition(premise, &mapping)?;
+        match proposition_db.get_proposition_probability(connection, &term)? {
+            Some(part) => probability *= part,
+            None => return Ok(None),
+        }
+        terms.push(term);
+    }
+    Ok(Some(FactorContext {
+        factor: vec![PropositionFactor {
+            premise: PropositionGroup { terms },
+            conclusion: conclusion.clone(),
+            inference: candidate.clone(),
+        }],
+        probabilities: vec![probability],
+    }))
+}
+
+/// A proposed rule, with how much it improves on the base rate of its conclusion, per example.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScoredRule {
+    pub implication: ImplicationFactor,
+    pub examples: usize,
+    pub gain: f64,
+    pub accepted: bool,
+}
+
+fn base_rate(examples: &[(FactorContext, f64)]) -> f64 {
+    let sum: f64 = examples.iter().map(|(_, gold)| gold).sum();
+    (sum + BASE_RATE_PSEUDO_COUNT) / (examples.len() as f64 + 2f64 * BASE_RATE_PSEUDO_COUNT)
+}
+
+fn total_log_loss(
+    connection: &mut Connection,
+    model: &dyn FactorModel,
+    examples: &[(FactorContext, f64)],
+) -> Result<f64, Box<dyn Error>> {
+    let mut total = 0f64;
+    for (factor, gold) in examples {
+        let predicted = model.predict(connection, factor)?.probability;
+        total += TrainStatistics::new(predicted, *gold).log_loss;
+    }
+    Ok(total)
+}
+
+/// Trains a fresh `ExponentialModel` on `train` in the scratch namespace, and returns it.
+fn fit_candidate(
+    connection: &mut Connection,
+    scratch_namespace: &str,
+    options: &CommandLineOptions,
+    candidate: &ImplicationFactor,
+    train: &[(FactorContext, f64)],
+) -> Result<Box<dyn FactorModel>, Box<dyn Error>> {
+    namespace_clear(connection, scratch_namespace)?;
+    let mut model =
+        ExponentialModel::new_mutable(connection, scratch_namespace.to_string(), options)?;
+    model.initialize_connection(connection, candidate)?;
+    for _ in 0..options.num_epochs {
+        for (factor, gold) in train {
+            model.train(connection, factor, *gold, 1f64)?;
+        }
+    }
+    Ok(model)
+}
+
+/// The per-example gain of `candidate` over the base rate: in held-out log loss, or in BIC.
+fn score_candidate(
+    connection: &mut Connection,
+    scratch_namespace: &str,
+    options: &CommandLineOptions,
+    candidate: &ImplicationFactor,
+    examples: Vec<(FactorContext, f64)>,
+) -> Result<f64, Box<dyn Error>> {
+    match options.structure_score.as_str() {
+        "heldout" => {
+            let (held_out, train): (Vec<_>, Vec<_>) = examples
+                .into_iter()
+                .enumerate()
+                .partition(|(index, _)| index % HELD_OUT_EVERY == 0);
+            let held_out: Vec<_> = held_out.into_iter().map(|(_, e)| e).collect();
+            let train: Vec<_> = train.into_iter().map(|(_, e)| e).collect();
+            if held_out.is_empty() || train.is_empty() {
+                return Ok(0f64);
+            }
+            let model = fit_candidate(connection, scratch_namespace, options, candidate, &train)?;
+            let model_loss = total_log_loss(connection, model.as_ref(), &held_out)?;
+            let rate = base_rate(&train);
+            let base_loss: f64 = held_out
+                .iter()
+                .map(|(_, gold)| TrainStatistics::new(rate, *gold).log_loss)
+                .sum();
+            Ok((base_loss - model_loss) / held_out.len() as f64)
+        }
+        "bic" => {
+            let n = examples.len() as f64;
+            let model =
+                fit_candidate(connection, scratch_namespace, options, candidate, &examples)?;
+            let model_loss = total_log_loss(connection, model.as_ref(), &examples)?;
+            let rate = base_rate(&examples);
+            let base_loss: f64 = examples
+                .iter()
+                .map(|(_, gold)| TrainStatistics::new(rate, *gold).log_loss)
+                .sum();
+            // The weights of the rule and the biases of its conclusion, against one base rate. The
+            // candidate is fit alone, so it has no interaction features.
+            let model_parameters = (implication_features(candidate).len()
+                + bias_features(&candidate.conclusion, options.domain_bias).len())
+                as f64;
+            let base_parameters = 1f64;
+            let model_bic = 2f64 * model_loss + model_parameters * n.ln();
+            let base_bic = 2f64 * base_loss + base_parameters * n.ln();
+            Ok((base_bic - model_bic) / n)
+        }
+        name => Err(format!("Unknown structure score type {}", name).into()),
+    }
+}
+
+/// Proposes rules of up to `--max_premises` terms for each relation in the training queue, scores
+/// each on its own against the base rate of its conclusion, and stores up to
+/// `--max_rules_per_relation` rules per relation whose gain is above `--min_rule_gain`.
+pub fn learn_structure(
+    connection: &mut Connection,
+    options: &CommandLineOptions,
+) -> Result<Vec<ScoredRule>, Box<dyn Error>> {
+    let namespace = options.scenario_name.clone();
+    let scratch_namespace = format!("{}_structure_search", namespace);
+    let mut graph = InferenceGraph::new_mutable(namespace.clone())?;
+    let proposition_db = RedisBeliefTable::new_mutable(connection, namespace.clone())?;
+    let plan = TrainingPlan::new(namespace.clone())?;
+    let training_questions = plan.get_training_questions(connection)?;
+    let mut schemas: Vec<RelationSchema> = vec![];
+    let mut questions_by_schema: HashMap<String, Vec<Proposition>> = HashMap::new();
+    for proposition in &training_questions {
+        if proposition.predicate.relation.relation_name == existence_predicate_name() {
+            continue;
+        }
+        let signature = proposition.predicate.domain_signature();
+        if !questions_by_schema.contains_key(&signature) {
+            schemas.push(RelationSchema::from_proposition(proposition));
+        }
+        questions_by_schema
+            .entry(signature)
+            .or_insert(vec![])
+            .push(proposition.clone());
+    }
+    schemas.sort_by_key(|schema| schema.quantified_predicate().domain_signature());
+    let existing: Vec<String> = graph
+        .get_all_implications(connection)?
+        .iter()
+        .map(|implication| implication.unique_key())
+        .collect();
+    let mut result = vec![];
+    for conclusion in &schemas {
+        let signature = conclusion.quantified_predicate().domain_signature();
+        let terms = candidate_terms(&schemas, conclusion);
+        let mut scored = vec![];
+        for indices in candidate_conjunctions(&terms, options.max_premises) {
+            let candidate = build_implication(&terms, &indices, conclusion);
+            if existing.contains(&candidate.unique_key()) {
+                continue;
+            }
+            let mut examples = vec![];
+            for question in &questions_by_schema[&signature] {
+                let gold = match proposition_db.get_proposition_probability(connection, question)? {
+                    Some(gold) => gold,
+                    None => continue,
+                };
+                if let Some(factor) =
+                    candidate_factor(connection, &proposition_db, &candidate, question)?
+                {
+                    examples.push((factor, gold));
+                }
+            }
+            let size = examples.len();
+            if size == 0 {
+                continue;
+            }
+            let gain = score_candidate(
+                connection,
+                &scratch_namespace,
+                options,
+                &candidate,
+                examples,
+            )?;
+            trace!("learn_structure - {} gain {}", candidate.unique_key(), gain);
+            scored.push(ScoredRule {
+                implication: candidate,
+                examples: size,
+                gain,
+                accepted: false,
+            });
+        }
+        scored.sort_by(|a, b| b.gain.total_cmp(&a.gain));
+        let mut accepted = vec![];
+        for rule in scored.iter_mut() {
+            if accepted.len() < options.max_rules_per_relation && rule.gain > options.min_rule_gain
+            {
+                rule.accepted = true;
+                accepted.push(rule.implication.clone());
+            }
+        }
+        graph.store_predicate_implications(connection, &accepted)?;
+        result.extend(scored);
+    }
+    namespace_clear(connection, &scratch_namespace)?;
+    Ok(result)
+}
@@ -1,22 +1,25 @@
 use crate::{
     common::{
         interface::BeliefTable,
-        redis::{seq_get_all, seq_push},
+        redis::{get_value, seq_clear, seq_get_all, seq_get_from, seq_push, set_value},
     },
     model::{
         self,
-        exponential::ExponentialModel,
+        factory::FactorModelFactory,
+        frozen::get_frozen_implications,
+        interaction::clear_interactions,
         objects::{
             Domain, Entity, ImplicationFactor, Predicate, PredicateGroup, Proposition,
             PropositionGroup,
         },
     },
-    print_yellow,
+    print_blue, print_yellow,
 };
 use redis::{Commands, Connection};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::{
     cell::RefCell,
+    collections::{HashMap, HashSet},
     error::Error,
     sync::{Arc, Mutex},
 };
@@ -24,7 +27,13 @@ use std::{
 use super::graph::InferenceGraph;
 use super::interface::ScenarioMaker;
 use super::model::FactorModel;
+use super::premise::PremiseEstimator;
+use super::negative::add_negative_samples;
+use super::parallel::train_in_parallel;
+use super::prior::RootPriors;
+use super::checkpoint::save_checkpoint;
 use super::resources::ResourceContext;
+use super::setup::CommandLineOptions;
 use super::{
     interface::{PredictStatistics, TrainStatistics},
     model::FactorContext,
@@ -32,6 +41,9 @@ use super::{
 };
 use crate::common::model::InferenceModel;
 use crate::common::proposition_db::RedisBeliefTable;
+use crate::inference::graph::PropositionFactor;
+use crate::inference::inference::ConvergenceOptions;
+use crate::inference::rounds::run_inference_with_stored_evidence;
 use crate::model::choose::extract_backimplications_from_proposition;
 use std::borrow::BorrowMut;
 
@@ -39,6 +51,22 @@ pub struct TrainingPlan {
     namespace: String,
 }
 
+/// A queue entry: the proposition, and how much it counts in training relative to the others.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrainingExample {
+    pub proposition: Proposition,
+    pub weight: f64,
+}
+
+/// The loss averaged over one pass through the training queue.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EpochStatistics {
+    pub epoch: usize,
+    pub examples: usize,
+    pub log_loss: f64,
+    pub squared_error: f64,
+}
+
 impl TrainingPlan {
     pub fn new(namespace: String) -> Result<Self, Box<dyn Error>> {
         Ok(TrainingPlan { namespace })
@@ -49,32 +77,40 @@ impl TrainingPlan {
         connection: &mut Connection,
         queue_name: &String,
         proposition: &Proposition,
+    ) -> Result<(), Box<dyn Error>> {
+        let example = TrainingExample {
+            proposition: proposition.clone(),
+            weight: 1f64,
+        };
+        self.add_example_to_queue(connection, queue_name, &example)
+    }
+
+    pub fn add_example_to_queue(
+        &mut self,
+        connection: &mut Connection,
+        queue_name: &String,
+        example: &TrainingExample,
     ) -> Result<(), Box<dyn Error>> {
         trace!(
-            "GraphicalModel::add_to_training_queue - Start. Input proposition: {:?}",
-            proposition
+            "GraphicalModel::add_to_training_queue - Start. Input example: {:?}",
+            example
         );
-        let serialized_proposition = match serde_json::to_string(proposition) {
+        let serialized_example = match serde_json::to_string(example) {
             Ok(record) => record,
             Err(e) => {
                 trace!(
-                    "GraphicalModel::add_to_training_queue - Error serializing proposition: {}",
+                    "GraphicalModel::add_to_training_queue - Error serializing example: {}",
                     e
                 );
                 return Err(Box::new(e));
             }
         };
         trace!(
-            "GraphicalModel::add_to_training_queue - Serialized proposition: {}",
-            &serialized_proposition
+            "GraphicalModel::add_to_training_queue - Serialized example: {}",
+            &serialized_example
         );
-        seq_push(
-            connection,
-            &self.namespace,
-            &queue_name,
-            &serialized_proposition,
-        )?;
-        trace!("GraphicalModel::add_to_training_queue - Proposition added to training queue successfully");
+        seq_push(connection, &self.namespace, &queue_name, &serialized_example)?;
+        trace!("GraphicalModel::add_to_training_queue - Example added to training queue successfully");
         Ok(())
     }
 
@@ -83,9 +119,24 @@ impl TrainingPlan {
         connection: &mut Connection,
         is_training: bool,
         proposition: &Proposition,
+    ) -> Result<(), Box<dyn Error>> {
+        self.maybe_add_weighted_to_training(connection, is_training, proposition, 1f64)
+    }
+
+    /// Like `maybe_add_to_training`, but the example's update is scaled by `weight`.
+    pub fn maybe_add_weighted_to_training(
+        &mut self,
+        connection: &mut Connection,
+        is_training: bool,
+        proposition: &Proposition,
+        weight: f64,
     ) -> Result<(), Box<dyn Error>> {
         if is_training {
-            self.add_proposition_to_queue(connection, &"training_queue".to_string(), &proposition)
+            let example = TrainingExample {
+                proposition: proposition.clone(),
+                weight,
+            };
+            self.add_example_to_queue(connection, &"training_queue".to_string(), &example)
         } else {
             Ok(())
         }
@@ -104,25 +155,37 @@ impl TrainingPlan {
         }
     }
 
-    fn get_propositions_from_queue(
+    fn get_examples_from_queue(
         &self,
         connection: &mut Connection,
         seq_name: &String,
-    ) -> Result<Vec<Proposition>, Box<dyn Error>> {
+    ) -> Result<Vec<TrainingExample>, Box<dyn Error>> {
         trace!(
-            "GraphicalModel::get_propositions_from_queue - Start. Queue name: {}",
+            "GraphicalModel::get_examples_from_queue - Start. Queue name: {}",
             seq_name
         );
         let records = seq_get_all(connection, &self.namespace, &seq_name)?;
         let mut result = vec![];
         for record in &records {
-            let proposition = deserialize_record(record)?;
-            result.push(proposition);
+            let example = deserialize_example(record)?;
+            result.push(example);
         }
-        trace!("GraphicalModel::get_propositions_from_queue - Retrieved and deserialized propositions successfully");
+        trace!("GraphicalModel::get_examples_from_queue - Retrieved and deserialized examples successfully");
         Ok(result)
     }
 
+    fn get_propositions_from_queue(
+        &self,
+        connection: &mut Connection,
+        seq_name: &String,
+    ) -> Result<Vec<Proposition>, Box<dyn Error>> {
+        let examples = self.get_examples_from_queue(connection, seq_name)?;
+        Ok(examples
+            .into_iter()
+            .map(|example| example.proposition)
+            .collect())
+    }
+
     pub fn get_training_questions(
         &self,
         connection: &mut Connection,
@@ -131,6 +194,51 @@ impl TrainingPlan {
         self.get_propositions_from_queue(connection, &training_queue_name)
     }
 
+    pub fn get_training_examples(
+        &self,
+        connection: &mut Connection,
+    ) -> Result<Vec<TrainingExample>, Box<dyn Error>> {
+        let training_queue_name = String::from("training_queue");
+        self.get_examples_from_queue(connection, &training_queue_name)
+    }
+
+    /// The training examples appended to the queue at or after index `cursor`.
+    pub fn get_training_examples_since(
+        &self,
+        connection: &mut Connection,
+        cursor: usize,
+    ) -> Result<Vec<TrainingExample>, Box<dyn Error>> {
+        let records = seq_get_from(connection, &self.namespace, "training_queue", cursor)?;
+        let mut result = vec![];
+        for record in &records {
+            result.push(deserialize_example(record)?);
+        }
+        Ok(result)
+    }
+
+    pub const TRAINING_CURSOR_KEY: &'static str = "training_cursor";
+
+    /// The number of training questions that have been trained on, so far.
+    pub fn get_training_cursor(&self, connection: &mut Connection) -> Result<usize, Box<dyn Error>> {
+        match get_value(connection, &self.namespace, Self::TRAINING_CURSOR_KEY)? {
+            Some(record) => Ok(record.parse::<usize>()?),
+            None => Ok(0),
+        }
+    }
+
+    pub fn store_training_cursor(
+        &mut self,
+        connection: &mut Connection,
+        cursor: usize,
+    ) -> Result<(), Box<dyn Error>> {
+        set_value(
+            connection,
+            &self.namespace,
+            Self::TRAINING_CURSOR_KEY,
+            &cursor.to_string(),
+        )
+    }
+
     pub fn get_test_questions(
         &self,
         connection: &mut Connection,
@@ -138,6 +246,34 @@ impl TrainingPlan {
         let test_queue_name = String::from("test_queue");
         self.get_propositions_from_queue(connection, &test_queue_name)
     }
+
+    pub const TRAINING_CURVE_KEY: &'static str = "training_curve";
+
+    pub fn store_epoch_statistics(
+        &mut self,
+        connection: &mut Connection,
+        statistics: &EpochStatistics,
+    ) -> Result<(), Box<dyn Error>> {
+        let record = serde_json::to_string(statistics)?;
+        seq_push(connection, &self.namespace, Self::TRAINING_CURVE_KEY, &record)?;
+        Ok(())
+    }
+
+    pub fn clear_training_curve(&mut self, connection: &mut Connection) -> Result<(), Box<dyn Error>> {
+        seq_clear(connection, &self.namespace, Self::TRAINING_CURVE_KEY)
+    }
+
+    pub fn get_training_curve(
+        &self,
+        connection: &mut Connection,
+    ) -> Result<Vec<EpochStatistics>, Box<dyn Error>> {
+        let records = seq_get_all(connection, &self.namespace, Self::TRAINING_CURVE_KEY)?;
+        let mut result = vec![];
+        for record in &records {
+            result.push(deserialize_record(record)?);
+        }
+        Ok(result)
+    }
 }
 
 fn deserialize_record<'a, T>(record: &'a str) -> Result<T, Box<dyn Error>>
@@ -147,33 +283,91 @@ where
     serde_json::from_str(record).map_err(|e| Box::new(e) as Box<dyn Error>)
 }
 
-// Probabilities are either 0 or 1, so assume independent, i.e., just boolean combine them as AND.
+/// Queues written before examples had weights hold bare propositions, which count with weight 1.
+fn deserialize_example(record: &str) -> Result<TrainingExample, Box<dyn Error>> {
+    match serde_json::from_str::<TrainingExample>(record) {
+        Ok(example) => Ok(example),
+        Err(_) => Ok(TrainingExample {
+            proposition: deserialize_record(record)?,
+            weight: 1f64,
+        }),
+    }
+}
+
+// The estimator decides how fractional term probabilities are combined into the conjunction.
+// Returns None if some term has no stored probability.
 fn extract_group_probability_for_training(
     connection: &mut Connection,
     proposition_db: &Box<dyn BeliefTable>,
-    premise: &PropositionGroup,
-) -> Result<f64, Box<dyn Error>> {
-    let mut product = 1f64;
-    for term in &premise.terms {
-        let part = proposition_db
-            .get_proposition_probability(connection, term)?
-            .unwrap();
-        product *= part;
+    estimator: &PremiseEstimator,
+    factor: &PropositionFactor,
+) -> Result<Option<f64>, Box<dyn Error>> {
+    let mut parts = vec![];
+    for term in &factor.premise.terms {
+        match proposition_db.get_proposition_probability(connection, term)? {
+            Some(part) => parts.push(part),
+            None => return Ok(None),
+        }
     }
-    Ok(product)
+    let probability = estimator.estimate(&factor.inference.unique_key(), &parts);
+    Ok(Some(probability))
 }
 
-fn extract_factor_for_proposition_for_training(
+// Returns None if some premise is not fully observed.
+pub fn extract_factor_for_proposition_for_training(
     connection: &mut Connection,
     proposition_db: &Box<dyn BeliefTable>,
+    estimator: &PremiseEstimator,
     graph: &InferenceGraph,
     conclusion: Proposition,
-) -> Result<FactorContext, Box<dyn Error>> {
+) -> Result<Option<FactorContext>, Box<dyn Error>> {
     let factors = extract_backimplications_from_proposition(connection, graph, &conclusion)?;
     let mut probabilities = vec![];
     for factor in &factors {
         let probability =
-            extract_group_probability_for_training(connection, proposition_db, &factor.premise)?;
+            extract_group_probability_for_training(connection, proposition_db, estimator, factor)?;
+        match probability {
+            Some(probability) => probabilities.push(probability),
+            None => return Ok(None),
+        }
+    }
+    let result = FactorContext {
+        factor: factors,
+        probabilities,
+    };
+    Ok(Some(result))
+}
+
+/// The E-step of EM: runs pi/lambda inference with all stored facts as evidence, including the
+/// conclusion, and uses the posterior probability of each premise group as its expected value.
+fn extract_expected_factor_for_training(
+    connection: &mut Connection,
+    model: &Arc<InferenceModel>,
+    proposition_db: &Box<dyn BeliefTable>,
+    conclusion: Proposition,
+    convergence: &ConvergenceOptions,
+) -> Result<FactorContext, Box<dyn Error>> {
+    let factors = extract_backimplications_from_proposition(connection, &model.graph, &conclusion)?;
+    let (table, report) = run_inference_with_stored_evidence(
+        connection,
+        model,
+        proposition_db,
+        &conclusion,
+        &HashSet::new(),
+        convergence,
+    )?;
+    if !report.converged {
+        warn!(
+            "do_training - E-step for {:?} did not converge, the expected premises may be off: {:?}",
+            conclusion, report
+        );
+    }
+    let mut probabilities = vec![];
+    for factor in &factors {
+        let probability = table
+            .get_group_marginal(&factor.premise)
+            .ok_or("Premise is missing from the marginal table.")?;
+        trace!("expected premise {:?} {}", &factor.premise, probability);
         probabilities.push(probability);
     }
     let result = FactorContext {
@@ -183,40 +377,280 @@ fn extract_factor_for_proposition_for_training(
     Ok(result)
 }
 
-pub fn do_training(resources: &ResourceContext, namespace: String) -> Result<(), Box<dyn Error>> {
+/// The summed loss of training on some of the questions.
+#[derive(Debug, Default, Clone)]
+pub struct LossTotals {
+    pub examples: usize,
+    pub log_loss: f64,
+    pub squared_error: f64,
+}
+
+impl LossTotals {
+    pub fn add(&mut self, other: &LossTotals) {
+        self.examples += other.examples;
+        self.log_loss += other.log_loss;
+        self.squared_error += other.squared_error;
+    }
+}
+
+/// Weights that give the positive and negative examples of each conclusion relation the same
+/// total weight, for `--class_balance relation`, so that rare positives are not ignored.
+pub struct ClassWeights {
+    /// The (positive, negative) weight of each relation that has examples of both classes.
+    by_relation: HashMap<String, (f64, f64)>,
+}
+
+impl ClassWeights {
+    pub fn new(
+        connection: &mut Connection,
+        class_balance: &str,
+        proposition_db: &Box<dyn BeliefTable>,
+        examples: &[TrainingExample],
+    ) -> Result<ClassWeights, Box<dyn Error>> {
+        let mut by_relation = HashMap::new();
+        match class_balance {
+            "none" => {}
+            "relation" => {
+                let mut counts: HashMap<String, (f64, f64)> = HashMap::new();
+                for example in examples {
+                    let probability = match proposition_db
+                        .get_proposition_probability(connection, &example.proposition)?
+                    {
+                        Some(probability) => probability,
+                        None => continue,
+                    };
+                    let relation_name = &example.proposition.predicate.relation.relation_name;
+                    let count = counts.entry(relation_name.clone()).or_insert((0f64, 0f64));
+                    count.0 += example.weight * probability;
+                    count.1 += example.weight * (1f64 - probability);
+                }
+                for (relation_name, (positive, negative)) in counts {
+                    if positive > 0f64 && negative > 0f64 {
+                        let total = positive + negative;
+                        let weights = (total / (2f64 * positive), total / (2f64 * negative));
+                        trace!("ClassWeights - {} {:?}", relation_name, weights);
+                        by_relation.insert(relation_name, weights);
+                    }
+                }
+            }
+            name => return Err(format!("Unknown class balance type {}", name).into()),
+        }
+        Ok(ClassWeights { by_relation })
+    }
+
+    /// The factor on the weight of an example with gold `probability`; 1 if its relation is not
+    /// balanced.
+    pub fn example_factor(&self, proposition: &Proposition, probability: f64) -> f64 {
+        match self
+            .by_relation
+            .get(&proposition.predicate.relation.relation_name)
+        {
+            Some((positive, negative)) => probability * positive + (1f64 - probability) * negative,
+            None => 1f64,
+        }
+    }
+}
+
+/// One pass of `factor_model` over `examples`. Examples with no stored probability, or with
+/// unobserved premises outside of EM mode, are skipped.
+pub fn train_on_examples(
+    connection: &mut Connection,
+    factor_model: &mut Box<dyn FactorModel>,
+    proposition_db: &Box<dyn BeliefTable>,
+    estimator: &PremiseEstimator,
+    class_weights: &ClassWeights,
+    graph: &InferenceGraph,
+    inference_model: &Option<Arc<InferenceModel>>,
+    convergence: &ConvergenceOptions,
+    examples: &[TrainingExample],
+) -> Result<LossTotals, Box<dyn Error>> {
+    let mut totals = LossTotals::default();
+    for example in examples {
+        let proposition = &example.proposition;
+        trace!("do_training - Processing proposition: {:?}", proposition);
+        let probabiity_opt = proposition_db.get_proposition_probability(connection, proposition)?;
+        let probability = match probabiity_opt {
+            Some(probability) => probability,
+            None => {
+                warn!("do_training - No probability stored for {:?}, skipping.", proposition);
+                continue;
+            }
+        };
+        let observed = extract_factor_for_proposition_for_training(
+            connection,
+            proposition_db,
+            estimator,
+            graph,
+            proposition.clone(),
+        )?;
+        let factor = match observed {
+            Some(factor) => factor,
+            None => match inference_model {
+                Some(model) => extract_expected_factor_for_training(
+                    connection,
+                    model,
+                    proposition_db,
+                    proposition.clone(),
+                    convergence,
+                )?,
+                None => {
+                    warn!(
+                        "do_training - Unobserved premises for {:?}, skipping; use --expectation_maximization to train on it.",
+                        proposition
+                    );
+                    continue;
+                }
+            },
+        };
+        trace!("do_training - Backimplications: {:?}", &factor);
+        let weight = example.weight * class_weights.example_factor(proposition, probability);
+        let stats = factor_model.train(connection, &factor, probability, weight)?;
+        totals.log_loss += stats.log_loss;
+        totals.squared_error += stats.squared_error;
+        totals.examples += 1;
+    }
+    Ok(totals)
+}
+
+pub fn do_training(
+    resources: &ResourceContext,
+    options: &CommandLineOptions,
+) -> Result<(), Box<dyn Error>> {
+    let namespace = options.scenario_name.clone();
     let mut connection = resources.connection.lock().unwrap();
-    let graph = InferenceGraph::new_mutable(namespace.clone())?;
-    let proposition_db = RedisBeliefTable::new_mutable(namespace.clone())?;
-    let plan = TrainingPlan::new(namespace.clone())?;
-    let mut factor_model = ExponentialModel::new_mutable(namespace.clone())?;
+    let mut graph = InferenceGraph::new_mutable(namespace.clone())?;
+    for relation_name in &options.closed_world_relations {
+        graph.register_closed_world(&mut connection, relation_name)?;
+    }
+    for relation_name in &options.open_world_relations {
+        graph.unregister_closed_world(&mut connection, relation_name)?;
+    }
+    let proposition_db = RedisBeliefTable::new_mutable(&mut connection, namespace.clone())?;
+    let mut plan = TrainingPlan::new(namespace.clone())?;
+    let mut factor_model =
+        FactorModelFactory::new_mutable(&mut connection, namespace.clone(), options)?;
+    FactorModelFactory::store_model_name(&mut connection, &namespace, &options.factor_model)?;
     trace!("do_training - Getting all implications");
     let implications = graph.get_all_implications(&mut connection)?;
+    // Incremental training continues from the stored weights, like --resume_training.
+    let resume_training = options.resume_training || options.incremental;
+    // Frozen implications are set to their fixed values even when resuming, in case they were
+    // frozen after the last run.
+    let frozen = get_frozen_implications(&mut connection, &namespace)?;
+    if !frozen.is_empty() && options.factor_model == "monolithic" {
+        warn!("do_training - The monolithic model has no per-implication weights to freeze.");
+    }
+    if !resume_training {
+        clear_interactions(&mut connection, &namespace)?;
+    }
     for implication in implications {
+        let is_frozen = frozen.contains_key(&implication.unique_key());
+        if resume_training
+            && !is_frozen
+            && factor_model.is_connection_initialized(&mut connection, &implication)?
+        {
+            trace!("do_training - Resuming implication: {:?}", implication);
+            continue;
+        }
         print_yellow!("do_training - Processing implication: {:?}", implication);
         factor_model.initialize_connection(&mut connection, &implication)?;
     }
     trace!("do_training - Getting all propositions");
     let training_questions = plan.get_training_questions(&mut connection)?;
+    let cursor = if options.incremental {
+        plan.get_training_cursor(&mut connection)?
+    } else {
+        0
+    };
+    // The priors and premise estimates are cheap, and are recomputed over the whole queue.
+    let new_examples = plan.get_training_examples_since(&mut connection, cursor)?;
     trace!(
-        "do_training - Processing propositions: {}",
+        "do_training - Processing propositions: {} of {}",
+        new_examples.len(),
         training_questions.len()
     );
+    let mut priors = RootPriors::new(namespace.clone())?;
+    priors.learn_priors(&mut connection, &proposition_db, &graph, &training_questions)?;
+    let estimator = PremiseEstimator::new(
+        &mut connection,
+        &options.premise_estimator,
+        &proposition_db,
+        &graph,
+        &training_questions,
+    )?;
+    let training_examples = plan.get_training_examples(&mut connection)?;
+    let class_weights = ClassWeights::new(
+        &mut connection,
+        &options.class_balance,
+        &proposition_db,
+        &training_examples,
+    )?;
+    // Reads the weights as they are updated, to compute the expectations in EM mode.
+    let inference_model = if options.expectation_maximization {
+        Some(InferenceModel::new_shared(&mut connection, namespace.clone())?)
+    } else {
+        None
+    };
+    let mut curve = plan.get_training_curve(&mut connection)?;
+    if !resume_training {
+        plan.clear_training_curve(&mut connection)?;
+        curve.clear();
+    }
     let mut examples_processed = 0;
-    for proposition in &training_questions {
-        trace!("do_training - Processing proposition: {:?}", proposition);
-        let factor = extract_factor_for_proposition_for_training(
+    for _ in 0..options.num_epochs {
+        let epoch = curve.len();
+        let epoch_examples = add_negative_samples(
             &mut connection,
-            &proposition_db,
             &graph,
-            proposition.clone(),
+            &new_examples,
+            options.negative_ratio,
+            epoch,
         )?;
-        trace!("do_training - Backimplications: {:?}", &factor);
-        let probabiity_opt =
-            proposition_db.get_proposition_probability(&mut connection, proposition)?;
-        let probability = probabiity_opt.expect("Probability should exist.");
-        let _stats = factor_model.train(&mut connection, &factor, probability)?;
-        examples_processed += 1;
+        let totals = if options.num_workers > 1 {
+            train_in_parallel(
+                &mut connection,
+                options,
+                &estimator,
+                &class_weights,
+                &epoch_examples,
+            )?
+        } else {
+            train_on_examples(
+                &mut connection,
+                &mut factor_model,
+                &proposition_db,
+                &estimator,
+                &class_weights,
+                &graph,
+                &inference_model,
+                &resources.convergence,
+                &epoch_examples,
+            )?
+        };
+        let examples = totals.examples;
+        examples_processed += examples;
+        let statistics = EpochStatistics {
+            epoch,
+            examples,
+            log_loss: totals.log_loss / examples.max(1) as f64,
+            squared_error: totals.squared_error / examples.max(1) as f64,
+        };
+        if options.print_training_loss {
+            print_blue!(
+                "epoch {}: examples {}, log_loss {:.6}, squared_error {:.6}",
+                statistics.epoch,
+                statistics.examples,
+                statistics.log_loss,
+                statistics.squared_error
+            );
+        }
+        plan.store_epoch_statistics(&mut connection, &statistics)?;
+        if options.checkpoint_every_epoch {
+            save_checkpoint(&mut connection, &namespace, &format!("epoch-{}", statistics.epoch))?;
+        }
+        curve.push(statistics);
     }
+    plan.store_training_cursor(&mut connection, cursor + new_examples.len())?;
     trace!(
         "do_training - Training complete: examples processed {}",
         examples_processed
@@ -227,12 +661,14 @@ pub fn do_training(resources: &ResourceContext, namespace: String) -> Result<(),
 pub fn setup_and_train(
     resources: &ResourceContext,
     scenario_maker: &dyn ScenarioMaker,
-    namespace: &str,
+    options: &CommandLineOptions,
 ) -> Result<(), Box<dyn Error>> {
     let model_spec = "dummy_model_spec".to_string();
-    let result = scenario_maker.setup_scenario(resources);
-    trace!("scenario result: {:?}", result);
-    let train_result = do_training(resources, namespace.to_string());
-    trace!("train result: {:?}", train_result);
+    // Incremental training picks up facts added since the last run, e.g., by `import`, so the
+    // scenario is not set up again.
+    if !options.incremental {
+        scenario_maker.setup_scenario(resources)?;
+    }
+    do_training(resources, options)?;
     Ok(())
 }
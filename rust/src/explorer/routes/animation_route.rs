@@ -94,13 +94,16 @@ fn backwards_print_single_with_marginal_table(
 fn safe_network_animations(
     connection: &mut Connection,
     namespace: &str,
+    checkpoint: &Option<String>,
     marginal_tables: &Vec<MarginalTable>,
 ) -> Result<String, Box<dyn Error>> {
     let graph = InferenceGraph::new_shared(namespace.to_string())?;
     let target = graph.get_target(connection)?;
     let proposition_graph = PropositionGraph::new_shared(connection, &graph, target)?;
     proposition_graph.visualize();
-    let model = InferenceModel::new_shared(namespace.to_string()).unwrap();
+    let model =
+        InferenceModel::new_shared_at_checkpoint(connection, namespace.to_string(), checkpoint)
+            .unwrap();
     let fact_memory = EmptyBeliefTable::new_shared(namespace)?;
     let inferencer =
         Inferencer::new_mutable(model.clone(), proposition_graph.clone(), fact_memory)?;
@@ -124,10 +127,18 @@ pub fn internal_animation(
     resource_context: &ResourceContext,
 ) -> Html<String> {
     let mut connection = resource_context.connection.lock().unwrap();
-    let marginal_tables = run_inference_rounds(&mut connection, experiment_name, test_scenario)
-        .expect("Testing failed.");
+    let checkpoint = &resource_context.checkpoint;
+    let (marginal_tables, _report) = run_inference_rounds(
+        &mut connection,
+        experiment_name,
+        test_scenario,
+        checkpoint,
+        &resource_context.convergence,
+    )
+    .expect("Testing failed.");
     let body_html =
-        safe_network_animations(&mut connection, experiment_name, &marginal_tables).unwrap();
+        safe_network_animations(&mut connection, experiment_name, checkpoint, &marginal_tables)
+            .unwrap();
     // let result = render_app_body(&body_html);
     let body_path = "src/explorer/assets/slides.html";
     let result = render_against_custom_body(&body_html, &body_path);
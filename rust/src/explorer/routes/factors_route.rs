@@ -79,9 +79,14 @@ fn iterate_through_factors(
     scenario_name: &str,
     resource_context: &ResourceContext,
 ) -> Result<String, Box<dyn Error>> {
-    let model = InferenceModel::new_shared(scenario_name.to_string()).unwrap();
-    let fact_memory = EmptyBeliefTable::new_shared(scenario_name)?;
     let mut connection = resource_context.connection.lock().unwrap();
+    let model = InferenceModel::new_shared_at_checkpoint(
+        &mut connection,
+        scenario_name.to_string(),
+        &resource_context.checkpoint,
+    )
+    .unwrap();
+    let fact_memory = EmptyBeliefTable::new_shared(scenario_name)?;
     let target = model.graph.get_target(&mut connection)?;
     let proposition_graph = PropositionGraph::new_shared(&mut connection, &model.graph, target)?;
     let inferencer =
@@ -5,10 +5,22 @@ use crate::{common::resources::ResourceContext, explorer::render_utils::render_a
 
 pub fn internal_marginals(experiment_name: &str, test_scenario: &str, resource_context: &ResourceContext) -> Html<String> {
     let mut connection = resource_context.connection.lock().unwrap();
-    let marginal_tables = run_inference_rounds(&mut connection, experiment_name, test_scenario)
-        .expect("Testing failed.");
+    let (marginal_tables, report) = run_inference_rounds(
+        &mut connection,
+        experiment_name,
+        test_scenario,
+        &resource_context.checkpoint,
+        &resource_context.convergence,
+    )
+    .expect("Testing failed.");
 
     let mut body_html = "".to_string();
+    body_html += &format!(
+        "<p>{} after {} sweeps, largest message change {:.2e}</p>",
+        if report.converged { "Converged" } else { "Did not converge" },
+        report.iterations,
+        report.max_message_change
+    );
     body_html += &format!("<div class='marginal_box'>");
     for marginal_table in &marginal_tables {
         let html_part = marginal_table.render_marginal_table();
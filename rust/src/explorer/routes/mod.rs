@@ -4,4 +4,5 @@ pub mod factors_route;
 pub mod index_route;
 pub mod marginals_route;
 pub mod network_route;
+pub mod training_route;
 pub mod weights_route;
\ No newline at end of file
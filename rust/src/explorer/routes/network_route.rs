@@ -88,7 +88,12 @@ fn render_network(bundle: &ResourceContext, namespace: &str) -> Result<String, B
     let target = graph.get_target(&mut connection)?;
     let proposition_graph = PropositionGraph::new_shared(&mut connection, &graph, target)?;
     proposition_graph.visualize();
-    let model = InferenceModel::new_shared(namespace.to_string()).unwrap();
+    let model = InferenceModel::new_shared_at_checkpoint(
+        &mut connection,
+        namespace.to_string(),
+        &bundle.checkpoint,
+    )
+    .unwrap();
     let fact_memory = EmptyBeliefTable::new_shared(namespace)?;
     let inferencer =
         Inferencer::new_mutable(model.clone(), proposition_graph.clone(), fact_memory)?;
@@ -0,0 +1,91 @@
+use redis::Connection;
+use rocket::response::content::Html;
+
+use crate::{
+    common::{
+        resources::ResourceContext,
+        train::{EpochStatistics, TrainingPlan},
+    },
+    explorer::render_utils::render_app_body,
+};
+
+const CHART_WIDTH: f64 = 800f64;
+const CHART_HEIGHT: f64 = 300f64;
+
+fn render_curve_line(curve: &[EpochStatistics], values: &[f64], css_class: &str) -> String {
+    let max_value = values.iter().cloned().fold(f64::MIN_POSITIVE, f64::max);
+    let last_index = (curve.len().max(2) - 1) as f64;
+    let points: Vec<String> = values
+        .iter()
+        .enumerate()
+        .map(|(index, value)| {
+            let x = CHART_WIDTH * index as f64 / last_index;
+            let y = CHART_HEIGHT * (1f64 - value / max_value);
+            format!("{:.2},{:.2}", x, y)
+        })
+        .collect();
+    format!(
+        "<polyline class='{css_class}' points='{points}' />",
+        points = points.join(" ")
+    )
+}
+
+fn render_curve_chart(curve: &[EpochStatistics]) -> String {
+    let log_losses: Vec<f64> = curve.iter().map(|s| s.log_loss).collect();
+    let squared_errors: Vec<f64> = curve.iter().map(|s| s.squared_error).collect();
+    format!(
+        r#"
+        <svg class='training_chart' viewBox='0 0 {CHART_WIDTH} {CHART_HEIGHT}'>
+            {log_loss_line}
+            {squared_error_line}
+        </svg>
+        <div class='row_element'>
+            <span class='log_loss_legend'>log loss</span>
+            <span class='squared_error_legend'>squared error</span>
+            (each scaled to its own maximum)
+        </div>
+    "#,
+        log_loss_line = render_curve_line(curve, &log_losses, "log_loss_line"),
+        squared_error_line = render_curve_line(curve, &squared_errors, "squared_error_line"),
+    )
+}
+
+fn render_curve_table(curve: &[EpochStatistics]) -> String {
+    let mut html = String::from(
+        "<table><tr><th>Epoch</th><th>Examples</th><th>Log Loss</th><th>Squared Error</th></tr>",
+    );
+    for statistics in curve {
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{:.6}</td><td>{:.6}</td></tr>",
+            statistics.epoch, statistics.examples, statistics.log_loss, statistics.squared_error
+        ));
+    }
+    html.push_str("</table>");
+    html
+}
+
+fn render_training_part(connection: &mut Connection, plan: &TrainingPlan) -> String {
+    let mut buffer = String::from(
+        r#"
+        <div class='section_header'>
+            Training Curve
+        </div>
+    "#,
+    );
+    let curve = plan.get_training_curve(connection).unwrap();
+    if curve.is_empty() {
+        buffer += "<div class='row_element'>No training statistics stored.</div>";
+        return buffer;
+    }
+    buffer += &render_curve_chart(&curve);
+    buffer += &render_curve_table(&curve);
+    buffer
+}
+
+pub fn internal_training(experiment_name: &str, resources: &ResourceContext) -> Html<String> {
+    let mut connection = resources.connection.lock().unwrap();
+    let plan = TrainingPlan::new(experiment_name.to_string()).unwrap();
+    let body_html = render_training_part(&mut connection, &plan);
+    let result = render_app_body(&body_html);
+    Html(result.unwrap())
+}
@@ -2,20 +2,30 @@ use redis::Connection;
 use rocket::response::content::Html;
 
 use crate::{
-    common::{graph::InferenceGraph, resources::ResourceContext},
+    common::{
+        checkpoint::checkpoint_namespace, graph::InferenceGraph, resources::ResourceContext,
+    },
     explorer::{diagram_utils::diagram_implication, render_utils::render_app_body},
     model::{
+        exponential::get_domain_bias,
+        factory::FactorModelFactory,
+        frozen::get_frozen_implications,
+        interaction::get_recorded_interactions,
+        noisy_or::{activation_feature, leak_feature, NoisyOrParameters},
         objects::ImplicationFactor,
-        weights::{negative_feature, positive_feature, ExponentialWeights, CLASS_LABELS},
+        weights::{
+            bias_feature, bias_keys, interaction_feature, negative_feature, positive_feature, ExponentialWeights,
+            CLASS_LABELS,
+        },
     },
 };
 
 fn render_one_weight_box(
     connection: &mut Connection,
-    graph: &InferenceGraph,
+    model_namespace: &str,
     factor: &ImplicationFactor,
 ) -> String {
-    let weights = ExponentialWeights::new(graph.namespace.clone()).unwrap();
+    let weights = ExponentialWeights::new(model_namespace.to_string()).unwrap();
     let feature = factor.unique_key();
     let mut buffer = "".to_string();
     buffer += &format!("<div class='weight_box'>");
@@ -72,7 +82,134 @@ fn render_one_weight_box(
     buffer
 }
 
-fn render_weights_part(connection: &mut Connection, graph: &InferenceGraph) -> String {
+fn render_one_activation_box(
+    connection: &mut Connection,
+    model_namespace: &str,
+    factor: &ImplicationFactor,
+) -> String {
+    let parameters = NoisyOrParameters::new(model_namespace.to_string()).unwrap();
+    let feature = activation_feature(&factor.unique_key());
+    let activation = parameters
+        .read_probability(connection, &feature)
+        .unwrap()
+        .unwrap_or(0f64);
+    format!(
+        r#"
+        <div class='weight_box'>
+            <div class='weight_box_row'>
+                <div class='weight_box_cell'>
+                    activation
+                </div>
+                <div class='weight_box_cell positive_weight'>
+                    {activation}
+                </div>
+            </div>
+        </div>
+    "#
+    )
+}
+
+fn weight_css(weight: f64) -> &'static str {
+    if weight > 0.1f64 {
+        "positive_weight"
+    } else if weight < -0.1f64 {
+        "negative_weight"
+    } else {
+        "neutral_weight"
+    }
+}
+
+fn render_bias_part(
+    connection: &mut Connection,
+    graph: &InferenceGraph,
+    model_namespace: &str,
+) -> String {
+    let weights = ExponentialWeights::new(model_namespace.to_string()).unwrap();
+    let domain_bias = get_domain_bias(connection, model_namespace).unwrap();
+    let mut buffer = format!(
+        r#"
+        <div class='section_header'>
+            Bias Features
+        </div>
+    "#
+    );
+    let mut all_keys = vec![];
+    for implication in &graph.get_all_implications(connection).unwrap() {
+        for key in bias_keys(&implication.conclusion, domain_bias) {
+            if !all_keys.contains(&key) {
+                all_keys.push(key);
+            }
+        }
+    }
+    all_keys.sort();
+    for key in &all_keys {
+        buffer += &format!("<div class='row_element'>{key}</div>");
+        buffer += "<div class='weight_box'>";
+        for class_label in CLASS_LABELS {
+            let feature = bias_feature(key, class_label);
+            let weight = weights.read_single_weight(connection, &feature).unwrap();
+            let css = weight_css(weight);
+            buffer += &format!(
+                r#"
+                <div class='weight_box_row'>
+                    <div class='weight_box_cell'>
+                        {class_label}
+                    </div>
+                    <div class='weight_box_cell {css}'>
+                        {weight}
+                    </div>
+                </div>
+            "#
+            );
+        }
+        buffer += "</div>";
+    }
+    buffer
+}
+
+fn render_interactions_part(
+    connection: &mut Connection,
+    model_namespace: &str,
+) -> String {
+    let weights = ExponentialWeights::new(model_namespace.to_string()).unwrap();
+    let mut buffer = format!(
+        r#"
+        <div class='section_header'>
+            Interaction Features
+        </div>
+    "#
+    );
+    let interactions = get_recorded_interactions(connection, model_namespace).unwrap();
+    for interaction in &interactions {
+        buffer += &format!("<div class='row_element'>{interaction}</div>");
+        buffer += "<div class='weight_box'>";
+        for class_label in CLASS_LABELS {
+            let feature = interaction_feature(interaction, class_label);
+            let weight = weights.read_single_weight(connection, &feature).unwrap();
+            let css = weight_css(weight);
+            buffer += &format!(
+                r#"
+                <div class='weight_box_row'>
+                    <div class='weight_box_cell'>
+                        {class_label}
+                    </div>
+                    <div class='weight_box_cell {css}'>
+                        {weight}
+                    </div>
+                </div>
+            "#
+            );
+        }
+        buffer += "</div>";
+    }
+    buffer
+}
+
+fn render_weights_part(
+    connection: &mut Connection,
+    graph: &InferenceGraph,
+    model_namespace: &str,
+) -> String {
     let mut buffer = format!(
         r#"
         <div class='section_header'>
@@ -80,11 +217,37 @@ fn render_weights_part(connection: &mut Connection, graph: &InferenceGraph) -> S
         </div>
     "#
     );
+    let model_name = FactorModelFactory::get_model_name(connection, model_namespace).unwrap();
+    let frozen = get_frozen_implications(connection, model_namespace).unwrap();
     let all_relations = graph.get_all_implications(connection).unwrap();
+    if model_name == "noisy_or" {
+        let parameters = NoisyOrParameters::new(model_namespace.to_string()).unwrap();
+        let mut relation_names: Vec<&String> = all_relations
+            .iter()
+            .map(|implication| &implication.conclusion.relation.relation_name)
+            .collect();
+        relation_names.sort();
+        relation_names.dedup();
+        for relation_name in relation_names {
+            let leak = parameters
+                .read_probability(connection, &leak_feature(relation_name))
+                .unwrap()
+                .unwrap_or(0f64);
+            buffer += &format!("<div class='row_element'>noisy_or leak {relation_name} {leak}</div>");
+        }
+    }
     println!("all_relations {:?}", &all_relations);
     for relation in &all_relations {
         buffer += &diagram_implication(relation);
-        buffer += &render_one_weight_box(connection, graph, relation);
+        if let Some(value) = frozen.get(&relation.unique_key()) {
+            let description = value.describe();
+            buffer += &format!("<div class='row_element'>{description}</div>");
+        }
+        if model_name == "noisy_or" {
+            buffer += &render_one_activation_box(connection, model_namespace, relation);
+        } else {
+            buffer += &render_one_weight_box(connection, model_namespace, relation);
+        }
     }
     buffer
 }
@@ -92,7 +255,15 @@ fn render_weights_part(connection: &mut Connection, graph: &InferenceGraph) -> S
 pub fn internal_weights(experiment_name: &str, resources: &ResourceContext) -> Html<String> {
     let mut connection = resources.connection.lock().unwrap();
     let graph = InferenceGraph::new_mutable(experiment_name.to_string()).unwrap();
-    let body_html = render_weights_part(&mut connection, &graph);
+    let model_namespace = checkpoint_namespace(experiment_name, &resources.checkpoint);
+    let mut body_html = render_weights_part(&mut connection, &graph, &model_namespace);
+    let model_name = FactorModelFactory::get_model_name(&mut connection, &model_namespace).unwrap();
+    if model_name != "noisy_or" {
+        body_html += &render_bias_part(&mut connection, &graph, &model_namespace);
+    }
+    if model_name == "interaction" {
+        body_html += &render_interactions_part(&mut connection, &model_namespace);
+    }
     let result = render_app_body(&body_html);
     Html(result.unwrap())
 }
@@ -0,0 +1,127 @@
+use std::error::Error;
+
+use redis::Connection;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    inference::{Inferencer, MarginalTable},
+    joint::{JointModel, JointNode},
+};
+
+/// The largest number of single nodes that `run_exact_inference` will enumerate, i.e., 2^20
+/// joint assignments.
+pub const MAX_EXACT_SINGLES: usize = 20;
+
+/// The exact marginals of every node of the inferencer's proposition graph, by summing the
+/// `JointModel` over every assignment of the single nodes. This is exponential in the number of
+/// single nodes, so it is only meant to check the belief propagation marginals on small graphs.
+pub fn run_exact_inference(
+    connection: &mut Connection,
+    inferencer: &Inferencer,
+) -> Result<MarginalTable, Box<dyn Error>> {
+    let num_singles = inferencer
+        .bfs_order
+        .iter()
+        .filter(|node| node.is_single())
+        .count();
+    if num_singles > MAX_EXACT_SINGLES {
+        return Err(format!(
+            "Exact inference needs at most {} propositions, the graph has {}",
+            MAX_EXACT_SINGLES, num_singles
+        )
+        .into());
+    }
+    let joint = JointModel::new(connection, inferencer)?;
+    let num_groups = joint.groups.len();
+    let mut total = 0f64;
+    let mut single_true = vec![0f64; num_singles];
+    let mut group_true = vec![0f64; num_groups];
+    let mut values = vec![false; num_singles];
+    let mut group_values = vec![false; num_groups];
+    for assignment in 0..(1usize << num_singles) {
+        for (index, value) in values.iter_mut().enumerate() {
+            *value = assignment & (1 << index) != 0;
+        }
+        for (index, group_value) in group_values.iter_mut().enumerate() {
+            *group_value = joint.group_value(index, &values);
+        }
+        let mut weight = 1f64;
+        for (index, value) in values.iter().enumerate() {
+            weight *= joint.factor_probability(index, *value, &group_values)
+                * joint.likelihood(index, *value);
+            if weight == 0f64 {
+                break;
+            }
+        }
+        if weight == 0f64 {
+            continue;
+        }
+        total += weight;
+        for index in 0..num_singles {
+            if values[index] {
+                single_true[index] += weight;
+            }
+        }
+        for index in 0..num_groups {
+            if group_values[index] {
+                group_true[index] += weight;
+            }
+        }
+    }
+    if total == 0f64 {
+        return Err("The evidence has probability 0 under the model".into());
+    }
+    let mut entries = vec![];
+    for node in &joint.bfs_order {
+        let count = match node {
+            JointNode::Single(index) => single_true[*index],
+            JointNode::Group(index) => group_true[*index],
+        };
+        entries.push((format!("{:?}", joint.node(*node)), count / total));
+    }
+    Ok(MarginalTable::new(entries))
+}
+
+/// The absolute differences between two marginal tables over the same nodes.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MarginalComparison {
+    pub max_absolute_error: f64,
+    pub mean_absolute_error: f64,
+    /// The node with the largest difference.
+    pub worst_node: Option<String>,
+    pub num_nodes: usize,
+}
+
+/// Compares `approximate` against `exact`, over the nodes of `exact`. A node missing from
+/// `approximate` is an error.
+pub fn compare_marginals(
+    exact: &MarginalTable,
+    approximate: &MarginalTable,
+) -> Result<MarginalComparison, Box<dyn Error>> {
+    let mut max_absolute_error = 0f64;
+    let mut total_absolute_error = 0f64;
+    let mut worst_node = None;
+    for (key, exact_probability) in exact.entries() {
+        let approximate_probability = approximate
+            .get_by_key(key)
+            .ok_or_else(|| format!("{} has no approximate marginal", key))?;
+        let error = (approximate_probability - exact_probability).abs();
+        total_absolute_error += error;
+        if worst_node.is_none() || error > max_absolute_error {
+            max_absolute_error = error;
+            worst_node = Some(key.clone());
+        }
+    }
+    let num_nodes = exact.entries().len();
+    let mean_absolute_error = if num_nodes > 0 {
+        total_absolute_error / num_nodes as f64
+    } else {
+        0f64
+    };
+    Ok(MarginalComparison {
+        max_absolute_error,
+        mean_absolute_error,
+        worst_node,
+        num_nodes,
+    })
+}
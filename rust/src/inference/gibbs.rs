@@ -0,0 +1,176 @@
+use std::{collections::HashMap, error::Error};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use redis::Connection;
+
+use crate::common::{random::keyed_rng, setup::CommandLineOptions};
+
+use super::{
+    inference::{Inferencer, MarginalTable},
+    joint::{JointModel, JointNode},
+};
+
+/// The number of batches that the samples are split into for the batch means estimate of the
+/// standard error, which unlike the binomial one accounts for correlated samples.
+const NUM_BATCHES: usize = 20;
+
+#[derive(Debug, Clone)]
+pub struct GibbsOptions {
+    /// The number of samples kept.
+    pub num_samples: usize,
+    /// The number of sweeps run and discarded before the first sample.
+    pub burn_in: usize,
+    /// The number of sweeps per sample kept.
+    pub thinning: usize,
+    /// The seed of the sampler, `--seed` from the command line. With none, the global seed is used
+    /// if one was set, and otherwise entropy.
+    pub seed: Option<u64>,
+}
+
+impl GibbsOptions {
+    pub fn new(options: &CommandLineOptions) -> GibbsOptions {
+        GibbsOptions {
+            num_samples: options.gibbs_samples,
+            burn_in: options.gibbs_burn_in,
+            thinning: options.gibbs_thinning,
+            seed: options.seed,
+        }
+    }
+}
+
+/// Samples a value of `single` from its distribution given every other single, which only
+/// depends on its parent groups, the factors of its children and its evidence. Updates the groups
+/// that `single` is a term of.
+fn resample_single(
+    rng: &mut StdRng,
+    joint: &JointModel,
+    single: usize,
+    values: &mut [bool],
+    group_values: &mut [bool],
+) {
+    let mut weights = [0f64; 2];
+    for (outcome, weight) in weights.iter_mut().enumerate() {
+        let value = outcome == 1;
+        values[single] = value;
+        for group in &joint.term_of[single] {
+            group_values[*group] = joint.group_value(*group, values);
+        }
+        *weight =
+            joint.factor_probability(single, value, group_values) * joint.likelihood(single, value);
+        for child in &joint.children[single] {
+            *weight *= joint.factor_probability(*child, values[*child], group_values);
+        }
+    }
+    let total = weights[0] + weights[1];
+    // In a state of probability 0, e.g., right after initialization, keep the value and let the
+    // other singles move.
+    let value = if total > 0f64 {
+        rng.gen::<f64>() * total < weights[1]
+    } else {
+        values[single]
+    };
+    values[single] = value;
+    for group in &joint.term_of[single] {
+        group_values[*group] = joint.group_value(*group, values);
+    }
+}
+
+/// The marginals of every node of the inferencer's proposition graph, estimated by Gibbs sampling
+/// from the `JointModel`, with the evidence in the inferencer's fact memory. Each sweep resamples
+/// every single node in `bfs_order`; group nodes follow from their terms.
+///
+/// The table has the Monte Carlo standard error of each marginal, from batch means over the kept
+/// samples.
+pub fn run_gibbs_sampling(
+    connection: &mut Connection,
+    inferencer: &Inferencer,
+    gibbs: &GibbsOptions,
+) -> Result<MarginalTable, Box<dyn Error>> {
+    if gibbs.num_samples == 0 || gibbs.thinning == 0 {
+        return Err("Gibbs sampling needs at least one sample and a thinning of at least 1".into());
+    }
+    let joint = JointModel::new(connection, inferencer)?;
+    let mut rng = match gibbs.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => keyed_rng("gibbs sampling"),
+    };
+    let num_singles = joint.singles.len();
+    let num_groups = joint.groups.len();
+    let mut values = vec![false; num_singles];
+    let mut group_values = vec![false; num_groups];
+    // Start from a sample of each single given the singles before it in `bfs_order`, which is a
+    // forward sample where the graph has no loops.
+    for single in 0..num_singles {
+        let groups: Vec<usize> = (0..num_groups)
+            .filter(|group| joint.group_terms[*group].iter().all(|term| *term < single))
+            .collect();
+        for group in groups {
+            group_values[group] = joint.group_value(group, &values);
+        }
+        let weight_true =
+            joint.factor_probability(single, true, &group_values) * joint.likelihood(single, true);
+        let weight_false = joint.factor_probability(single, false, &group_values)
+            * joint.likelihood(single, false);
+        let total = weight_true + weight_false;
+        values[single] = total > 0f64 && rng.gen::<f64>() * total < weight_true;
+    }
+    for group in 0..num_groups {
+        group_values[group] = joint.group_value(group, &values);
+    }
+    let num_nodes = num_singles + num_groups;
+    let num_batches = NUM_BATCHES.min(gibbs.num_samples);
+    let mut batch_counts = vec![vec![0usize; num_nodes]; num_batches];
+    let mut batch_sizes = vec![0usize; num_batches];
+    let num_sweeps = gibbs.burn_in + gibbs.num_samples * gibbs.thinning;
+    let mut sample = 0;
+    for sweep in 0..num_sweeps {
+        for single in 0..num_singles {
+            resample_single(&mut rng, &joint, single, &mut values, &mut group_values);
+        }
+        if sweep < gibbs.burn_in || (sweep - gibbs.burn_in + 1) % gibbs.thinning != 0 {
+            continue;
+        }
+        let batch = sample * num_batches / gibbs.num_samples;
+        batch_sizes[batch] += 1;
+        for (node, value) in values.iter().chain(group_values.iter()).enumerate() {
+            if *value {
+                batch_counts[batch][node] += 1;
+            }
+        }
+        sample += 1;
+    }
+    trace!(
+        "run_gibbs_sampling - {} sweeps, {} samples in {} batches",
+        num_sweeps,
+        sample,
+        num_batches
+    );
+    let mut entries = vec![];
+    let mut standard_errors = HashMap::new();
+    for node in &joint.bfs_order {
+        // Samples are counted with the singles first and the groups after them.
+        let node_index = match node {
+            JointNode::Single(index) => *index,
+            JointNode::Group(index) => num_singles + *index,
+        };
+        let count: usize = batch_counts.iter().map(|counts| counts[node_index]).sum();
+        let mean = count as f64 / gibbs.num_samples as f64;
+        let standard_error = if num_batches > 1 {
+            let mut sum_squares = 0f64;
+            for (counts, size) in batch_counts.iter().zip(&batch_sizes) {
+                let batch_mean = counts[node_index] as f64 / *size as f64;
+                sum_squares += (batch_mean - mean).powi(2);
+            }
+            (sum_squares / (num_batches * (num_batches - 1)) as f64).sqrt()
+        } else {
+            (mean * (1f64 - mean) / gibbs.num_samples as f64).sqrt()
+        };
+        let node_string = format!("{:?}", joint.node(*node));
+        entries.push((node_string.clone(), mean));
+        standard_errors.insert(node_string, standard_error);
+    }
+    Ok(MarginalTable::new_with_standard_errors(
+        entries,
+        standard_errors,
+    ))
+}
@@ -11,7 +11,10 @@ use serde::{Deserialize, Serialize};
 use crate::{
     common::{graph::InferenceGraph, redis::RedisManager},
     model::{
-        choose::{compute_search_predicates, extract_backimplications_from_proposition},
+        choose::{
+            compute_search_predicates, extract_backimplications_from_proposition,
+            is_root_backimplications,
+        },
         objects::{GroupRoleMap, ImplicationFactor, Proposition, PropositionGroup},
     }, print_yellow,
 };
@@ -67,8 +70,8 @@ fn initialize_visit_single(
         inference_factors.len()
     );
 
-    if inference_factors.is_empty() {
-        trace!("\x1b[34mNo inference factors. Adding to roots.\x1b[0m");
+    if is_root_backimplications(&inference_factors) {
+        trace!("\x1b[34mNo inference factors but existence. Adding to roots.\x1b[0m");
         graph.roots.insert(single.clone());
     } else {
         for inference_factor in &inference_factors {
@@ -195,6 +198,7 @@ impl PropositionGraph {
                 }
             }
         }
+        sort_nodes(&mut r);
         trace!("Resulting vector: {:?}", r);
         r
     }
@@ -222,6 +226,7 @@ impl PropositionGraph {
                 }
             }
         }
+        sort_nodes(&mut r);
         trace!("Resulting vector: {:?}", r);
         r
     }
@@ -275,11 +280,22 @@ fn reverse_prune_duplicates(raw_order: &Vec<(i32, PropositionNode)>) -> Vec<Prop
     result
 }
 
+/// Sorts by name, so that the order does not depend on `HashSet` iteration.
+fn sort_nodes(nodes: &mut Vec<PropositionNode>) {
+    nodes.sort_by_key(|node| node.debug_string());
+}
+
 fn create_bfs_order(proposition_graph: &PropositionGraph) -> Vec<PropositionNode> {
     let mut queue = VecDeque::new();
     let mut buffer = vec![];
-    for root in &proposition_graph.roots {
-        queue.push_back((0, PropositionNode::from_single(&root)));
+    let mut roots: Vec<PropositionNode> = proposition_graph
+        .roots
+        .iter()
+        .map(|root| PropositionNode::from_single(root))
+        .collect();
+    sort_nodes(&mut roots);
+    for root in roots {
+        queue.push_back((0, root));
     }
     while let Some((depth, node)) = queue.pop_front() {
         buffer.push((depth, node.clone()));
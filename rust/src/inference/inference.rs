@@ -44,6 +44,9 @@ pub struct Inferencer {
 pub struct MarginalTable {
     entries: Vec<(String, f64)>,
     mapping: HashMap<String, f64>,
+    /// The Monte Carlo standard error of each marginal, for sampled tables.
+    #[serde(default)]
+    standard_errors: HashMap<String, f64>,
 }
 
 impl MarginalTable {
@@ -52,16 +55,49 @@ impl MarginalTable {
         for (key, value) in &entries {
             mapping.insert(key.clone(), *value);
         }
-        MarginalTable { entries, mapping }
+        MarginalTable {
+            entries,
+            mapping,
+            standard_errors: HashMap::new(),
+        }
+    }
+
+    pub fn new_with_standard_errors(
+        entries: Vec<(String, f64)>,
+        standard_errors: HashMap<String, f64>,
+    ) -> MarginalTable {
+        let mut table = MarginalTable::new(entries);
+        table.standard_errors = standard_errors;
+        table
     }
 }
 
 impl MarginalTable {
+    /// The `(node, probability)` pairs, in the order the table was built.
+    pub fn entries(&self) -> &Vec<(String, f64)> {
+        &self.entries
+    }
+
+    pub fn get_by_key(&self, node_string: &str) -> Option<f64> {
+        self.mapping.get(node_string).copied()
+    }
+
+    /// The Monte Carlo standard error of the marginal of the node, if the table was sampled.
+    pub fn get_standard_error(&self, node_string: &str) -> Option<f64> {
+        self.standard_errors.get(node_string).copied()
+    }
+
     pub fn get_marginal(&self, proposition: &Proposition) -> Option<f64> {
         let node_string = format!("{:?}", proposition);
         self.mapping.get(&node_string).copied()
     }
 
+    /// The probability that every term of `group` is true.
+    pub fn get_group_marginal(&self, group: &PropositionGroup) -> Option<f64> {
+        let node_string = format!("{:?}", group);
+        self.mapping.get(&node_string).copied()
+    }
+
     pub fn render_marginal_table(&self) -> String {
         let mut entries = self.entries.clone();
         // Sort entries by the string key in alphabetical order
@@ -72,6 +108,10 @@ impl MarginalTable {
 
         // Add rows to the table
         for (key, value) in entries {
+            let value = match self.get_standard_error(&key) {
+                Some(standard_error) => format!("{} &plusmn; {:.4}", value, standard_error),
+                None => value.to_string(),
+            };
             html_table.push_str(&format!("<tr><td>{}</td><td>{}</td></tr>", key, value));
         }
 
@@ -82,6 +122,51 @@ impl MarginalTable {
     }
 }
 
+/// When belief propagation stops: once no message changes by more than `tolerance` in a sweep, or
+/// after `max_sweeps` sweeps. Also how it gets there, which matters on graphs with loops.
+#[derive(Debug, Clone)]
+pub struct ConvergenceOptions {
+    pub tolerance: f64,
+    pub max_sweeps: usize,
+    /// The weight of the old message when a message is updated, 0 for no damping.
+    pub damping: f64,
+    /// `sweep` visits the nodes in `bfs_order` and back; `residual` visits them in order of the
+    /// largest change in their incoming messages.
+    pub schedule: String,
+}
+
+impl ConvergenceOptions {
+    pub fn new(options: &CommandLineOptions) -> ConvergenceOptions {
+        ConvergenceOptions {
+            tolerance: options.bp_tolerance,
+            max_sweeps: options.max_bp_sweeps,
+            damping: options.bp_damping,
+            schedule: options.bp_schedule.clone(),
+        }
+    }
+}
+
+impl Default for ConvergenceOptions {
+    fn default() -> Self {
+        ConvergenceOptions {
+            tolerance: 1e-6,
+            max_sweeps: 50,
+            damping: 0f64,
+            schedule: "sweep".to_string(),
+        }
+    }
+}
+
+/// How a run of belief propagation ended. A run that did not converge may be oscillating, so its
+/// marginals are not to be trusted.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ConvergenceReport {
+    pub iterations: usize,
+    pub converged: bool,
+    /// The largest message change in the last sweep.
+    pub max_message_change: f64,
+}
+
 impl Inferencer {
     pub fn new_mutable(
         model: Arc<InferenceModel>,
@@ -136,35 +221,86 @@ impl Inferencer {
         Ok(())
     }
 
+    /// One sweep. With the `sweep` schedule, this is a fan out from `evidence_node` if there is
+    /// one, or else a full forward and backward pass. Returns the largest change of a message.
+    pub fn do_sweep(
+        &mut self,
+        connection: &mut Connection,
+        evidence_node: &Option<PropositionNode>,
+        schedule: &str,
+    ) -> Result<f64, Box<dyn Error>> {
+        self.data.reset_message_change();
+        match (schedule, evidence_node) {
+            ("sweep", Some(node)) => self.do_fan_out_from_node(connection, node)?,
+            ("sweep", None) => self.do_full_forward_and_backward(connection)?,
+            ("residual", _) => self.do_residual_sweep(connection)?,
+            (name, _) => return Err(format!("Unknown schedule type {}", name).into()),
+        }
+        Ok(self.data.get_max_message_change())
+    }
+
+    /// Visits every node once, each time picking the node whose incoming messages changed the
+    /// most since it was last visited, so that new information spreads before stale messages are
+    /// recomputed. Ties go to the earlier node in `bfs_order`.
+    pub fn do_residual_sweep(&mut self, connection: &mut Connection) -> Result<(), Box<dyn Error>> {
+        let mut remaining = self.bfs_order.clone();
+        while !remaining.is_empty() {
+            let mut best = 0;
+            for (index, node) in remaining.iter().enumerate() {
+                if self.data.get_residual(node) > self.data.get_residual(&remaining[best]) {
+                    best = index;
+                }
+            }
+            let node = remaining.remove(best);
+            trace!("residual visit {:?} {}", &node, self.data.get_residual(&node));
+            self.data.clear_residual(&node);
+            self.pi_visit_node(connection, &node)?;
+            self.lambda_visit_node(connection, &node)?;
+        }
+        Ok(())
+    }
+
+    /// Sweeps until the messages converge or `convergence.max_sweeps` is reached. Returns the
+    /// marginals after each sweep.
+    pub fn run_until_converged(
+        &mut self,
+        connection: &mut Connection,
+        evidence_node: &Option<PropositionNode>,
+        convergence: &ConvergenceOptions,
+    ) -> Result<(Vec<MarginalTable>, ConvergenceReport), Box<dyn Error>> {
+        self.data.set_damping(convergence.damping);
+        let mut tables = vec![];
+        let mut report = ConvergenceReport {
+            iterations: 0,
+            converged: false,
+            max_message_change: f64::INFINITY,
+        };
+        while report.iterations < convergence.max_sweeps {
+            report.max_message_change = self.do_sweep(connection, evidence_node, &convergence.schedule)?;
+            report.iterations += 1;
+            tables.push(self.build_marginal_table()?);
+            if report.max_message_change <= convergence.tolerance {
+                report.converged = true;
+                break;
+            }
+        }
+        trace!("run_until_converged: {:?}", &report);
+        Ok((tables, report))
+    }
+
     pub fn update_marginals(&mut self) -> Result<MarginalTable, Box<dyn Error>> {
         println!("\nMARGINALS");
-        let mut entries = vec![];
-        for node in &self.bfs_order {
-            let pi0 = self.data.get_pi_value(node, 0).unwrap();
-            let pi1 = self.data.get_pi_value(node, 1).unwrap();
-            let lambda0 = self.data.get_lambda_value(node, 0).unwrap();
-            let lambda1 = self.data.get_lambda_value(node, 1).unwrap();
-            let potential0 = pi0 * lambda0;
-            let potential1 = pi1 * lambda1;
-            let norm = potential0 + potential1;
-            let probability0 = potential0 / norm;
-            let probability1 = potential1 / norm;
-
-            let formatted_prob0 = format!("{:.8}", probability0);
-            let formatted_prob1 = format!("{:.8}", probability1);
+        let table = self.build_marginal_table()?;
+        for (node_string, probability) in table.entries() {
+            let formatted_prob0 = format!("{:.8}", 1f64 - probability);
+            let formatted_prob1 = format!("{:.8}", probability);
             println!(
-                "{:<12} {:<12} {:?}",
+                "{:<12} {:<12} {}",
                 formatted_prob1.green(),
                 formatted_prob0.red(),
-                node
+                node_string
             );
-            let node_string = format!("{:?}", node);
-            let probability = probability1;
-            entries.push((node_string, probability));
         }
-
-        // self.log_table_to_file(&table)?;
-        let table = MarginalTable::new(entries);
         Ok(table)
     }
 
@@ -178,14 +314,9 @@ impl Inferencer {
             let potential0 = pi0 * lambda0;
             let potential1 = pi1 * lambda1;
             let norm = potential0 + potential1;
-            let probability0 = potential0 / norm;
             let probability1 = potential1 / norm;
-
-            let formatted_prob0 = format!("{:.8}", probability0);
-            let formatted_prob1 = format!("{:.8}", probability1);
             let node_string = format!("{:?}", node);
-            let probability = probability1;
-            entries.push((node_string, probability));
+            entries.push((node_string, probability1));
         }
         let table = MarginalTable::new(entries);
         Ok(table)
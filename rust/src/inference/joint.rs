@@ -0,0 +1,203 @@
+use std::{collections::HashMap, error::Error};
+
+use redis::Connection;
+
+use super::{
+    inference::{compute_factor_probability_table, Inferencer},
+    table::PropositionNode,
+};
+
+/// How a single node is scored in the joint distribution.
+enum SingleFactor {
+    /// P(node = true), for a root.
+    Prior(f64),
+    /// P(node = true | parents), indexed by the bits of the parent groups, in the order of
+    /// `parents`.
+    Conditional {
+        parents: Vec<usize>,
+        table: Vec<f64>,
+    },
+}
+
+/// A node of the proposition graph, by its index into `JointModel::singles` or
+/// `JointModel::groups`.
+#[derive(Debug, Clone, Copy)]
+pub enum JointNode {
+    Single(usize),
+    Group(usize),
+}
+
+/// The joint distribution of an inferencer's proposition graph, with the factor of each single
+/// node tabulated, so that assignments can be scored without calling the model again. Group nodes
+/// are the AND of their terms.
+///
+/// Each observed proposition with probability `p` contributes a likelihood of `p` when true and
+/// `1 - p` when false, the same as its lambda value in belief propagation, so evidence of 0 or 1
+/// conditions on the proposition.
+pub struct JointModel {
+    /// The single nodes, in `bfs_order`. Assignments are indexed the same way.
+    pub singles: Vec<PropositionNode>,
+    /// The group nodes, in `bfs_order`. Group values are indexed the same way.
+    pub groups: Vec<PropositionNode>,
+    /// Every node, singles and groups together, in `bfs_order`, which is the order that the
+    /// marginals are reported in.
+    pub bfs_order: Vec<JointNode>,
+    /// The terms of each group, as indices into `singles`.
+    pub group_terms: Vec<Vec<usize>>,
+    /// The groups that each single is a term of.
+    pub term_of: Vec<Vec<usize>>,
+    /// The singles whose factor depends on each single through a group, without duplicates.
+    pub children: Vec<Vec<usize>>,
+    /// The evidence probability of each single, if it is observed.
+    pub evidence: Vec<Option<f64>>,
+    factors: Vec<SingleFactor>,
+}
+
+impl JointModel {
+    pub fn new(
+        connection: &mut Connection,
+        inferencer: &Inferencer,
+    ) -> Result<JointModel, Box<dyn Error>> {
+        let nodes = &inferencer.bfs_order;
+        let singles: Vec<PropositionNode> = nodes
+            .iter()
+            .filter(|node| node.is_single())
+            .cloned()
+            .collect();
+        let groups: Vec<PropositionNode> = nodes
+            .iter()
+            .filter(|node| node.is_group())
+            .cloned()
+            .collect();
+        let single_index: HashMap<&PropositionNode, usize> = singles
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (node, index))
+            .collect();
+        let group_index: HashMap<&PropositionNode, usize> = groups
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (node, index))
+            .collect();
+        let bfs_order = nodes
+            .iter()
+            .map(|node| match single_index.get(node) {
+                Some(index) => JointNode::Single(*index),
+                None => JointNode::Group(group_index[node]),
+            })
+            .collect();
+        let mut group_terms = vec![];
+        let mut term_of = vec![vec![]; singles.len()];
+        for (index, group) in groups.iter().enumerate() {
+            let mut terms = vec![];
+            for term in &group.extract_group().terms {
+                let term_index = *single_index
+                    .get(&PropositionNode::from_single(term))
+                    .ok_or_else(|| format!("Term {:?} is not in the graph", term))?;
+                terms.push(term_index);
+                term_of[term_index].push(index);
+            }
+            group_terms.push(terms);
+        }
+        let mut group_children = vec![vec![]; groups.len()];
+        let mut factors = vec![];
+        let mut evidence = vec![];
+        for (index, single) in singles.iter().enumerate() {
+            let proposition = single.extract_single();
+            let factor = if inferencer.is_root(single) {
+                let prior = inferencer.model.root_probability(connection, &proposition)?;
+                SingleFactor::Prior(prior)
+            } else {
+                let parent_nodes = inferencer.proposition_graph.get_all_backward(single);
+                let mut parents = vec![];
+                for parent in &parent_nodes {
+                    let parent_index = *group_index
+                        .get(parent)
+                        .ok_or_else(|| format!("Parent {:?} is not in the graph", parent))?;
+                    parents.push(parent_index);
+                    group_children[parent_index].push(index);
+                }
+                let mut table = vec![0f64; 1 << parent_nodes.len()];
+                for (assignment, probability) in
+                    compute_factor_probability_table(connection, inferencer, single)?.pairs
+                {
+                    let mut bits = 0;
+                    for (position, parent) in parent_nodes.iter().enumerate() {
+                        if assignment.assignment_map[parent] {
+                            bits |= 1 << position;
+                        }
+                    }
+                    table[bits] = probability;
+                }
+                SingleFactor::Conditional { parents, table }
+            };
+            factors.push(factor);
+            evidence.push(
+                inferencer
+                    .fact_memory
+                    .get_proposition_probability(connection, &proposition)?,
+            );
+        }
+        let mut children = vec![];
+        for groups_of_single in &term_of {
+            let mut result: Vec<usize> = groups_of_single
+                .iter()
+                .flat_map(|group| group_children[*group].iter().copied())
+                .collect();
+            result.sort();
+            result.dedup();
+            children.push(result);
+        }
+        Ok(JointModel {
+            singles,
+            groups,
+            bfs_order,
+            group_terms,
+            term_of,
+            children,
+            evidence,
+            factors,
+        })
+    }
+
+    pub fn node(&self, node: JointNode) -> &PropositionNode {
+        match node {
+            JointNode::Single(index) => &self.singles[index],
+            JointNode::Group(index) => &self.groups[index],
+        }
+    }
+
+    pub fn group_value(&self, group: usize, values: &[bool]) -> bool {
+        self.group_terms[group].iter().all(|term| values[*term])
+    }
+
+    /// The probability of `single` having `value`, given the values of its parent groups.
+    pub fn factor_probability(&self, single: usize, value: bool, group_values: &[bool]) -> f64 {
+        let probability = match &self.factors[single] {
+            SingleFactor::Prior(prior) => *prior,
+            SingleFactor::Conditional { parents, table } => {
+                let mut bits = 0;
+                for (position, parent) in parents.iter().enumerate() {
+                    if group_values[*parent] {
+                        bits |= 1 << position;
+                    }
+                }
+                table[bits]
+            }
+        };
+        if value {
+            probability
+        } else {
+            1f64 - probability
+        }
+    }
+
+    /// The likelihood of the evidence on `single`, if it has `value`.
+    pub fn likelihood(&self, single: usize, value: bool) -> f64 {
+        match (self.evidence[single], value) {
+            (Some(probability), true) => probability,
+            (Some(probability), false) => 1f64 - probability,
+            (None, _) => 1f64,
+        }
+    }
+}
@@ -3,4 +3,7 @@ pub mod inference;
 pub mod graph;
 pub mod pi;
 pub mod lambda;
-pub mod rounds;
\ No newline at end of file
+pub mod rounds;
+pub mod exact;
+pub mod joint;
+pub mod gibbs;
\ No newline at end of file
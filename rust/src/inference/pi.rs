@@ -24,26 +24,30 @@ impl Inferencer {
         connection: &mut Connection,
         from_node: &PropositionNode,
     ) -> Result<(), Box<dyn Error>> {
-        if !self.is_root(from_node) {
-            let is_observed = self.is_observed(connection, from_node)?;
-            if is_observed {
-                self.pi_set_from_evidence(connection, from_node)?;
-            } else {
-                self.pi_compute_value(connection, &from_node)?;
-            }
+        // Evidence takes precedence over the prior of a root too, so that it reaches the
+        // children of the root.
+        if self.is_observed(connection, from_node)? {
+            self.pi_set_from_evidence(connection, from_node)?;
+        } else if self.is_root(from_node) {
+            self.pi_compute_root(connection, from_node)?;
         } else {
-            self.pi_compute_root(from_node)?;
+            self.pi_compute_value(connection, &from_node)?;
         }
         self.pi_send_messages(from_node)?;
         Ok(())
     }
 
-    fn pi_compute_root(&mut self, node: &PropositionNode) -> Result<(), Box<dyn Error>> {
+    fn pi_compute_root(
+        &mut self,
+        connection: &mut Connection,
+        node: &PropositionNode,
+    ) -> Result<(), Box<dyn Error>> {
         let root = node.extract_single();
+        let prior = self.model.root_probability(connection, &root)?;
         self.data
-            .set_pi_value(&PropositionNode::from_single(&root), 1, 1.0f64);
+            .set_pi_value(&PropositionNode::from_single(&root), 1, prior);
         self.data
-            .set_pi_value(&PropositionNode::from_single(&root), 0, 0.0f64);
+            .set_pi_value(&PropositionNode::from_single(&root), 0, 1f64 - prior);
         Ok(())
     }
 
@@ -1,10 +1,25 @@
-use std::error::Error;
+use std::{collections::HashSet, error::Error, sync::Arc};
 
 use redis::Connection;
 
-use crate::common::{model::InferenceModel, proposition_db::EmptyBeliefTable, resources::ResourceContext, test::ReplState};
+use crate::{
+    common::{
+        interface::BeliefTable,
+        model::InferenceModel,
+        proposition_db::{EmptyBeliefTable, HashMapBeliefTable},
+        resources::ResourceContext,
+        test::ReplState,
+    },
+    model::objects::Proposition,
+};
 
-use super::{graph::PropositionGraph, inference::{Inferencer, MarginalTable}, table::PropositionNode};
+use super::{
+    exact::{compare_marginals, run_exact_inference, MarginalComparison},
+    gibbs::{run_gibbs_sampling, GibbsOptions},
+    graph::PropositionGraph,
+    inference::{ConvergenceOptions, ConvergenceReport, Inferencer, MarginalTable},
+    table::PropositionNode,
+};
 
 fn setup_test_scenario(
     connection: &mut Connection,
@@ -32,12 +47,14 @@ fn setup_test_scenario(
     Ok(r)
 }
 
-pub fn run_inference_rounds(
+fn setup_scenario_inferencer(
     connection: &mut Connection,
     scenario_name: &str,
-    test_scenario: &str,
-) -> Result<Vec<MarginalTable>, Box<dyn Error>> {
-    let model = InferenceModel::new_shared(scenario_name.to_string()).unwrap();
+    checkpoint: &Option<String>,
+) -> Result<ReplState, Box<dyn Error>> {
+    let model =
+        InferenceModel::new_shared_at_checkpoint(connection, scenario_name.to_string(), checkpoint)
+            .unwrap();
     let fact_memory = EmptyBeliefTable::new_shared(scenario_name)?;
     let target = model.graph.get_target(connection)?;
     let proposition_graph = PropositionGraph::new_shared(connection, &model.graph, target)?;
@@ -45,22 +62,108 @@ pub fn run_inference_rounds(
     let mut inferencer =
         Inferencer::new_mutable(model.clone(), proposition_graph.clone(), fact_memory)?;
     inferencer.initialize_chart(connection)?;
-    let mut repl = ReplState::new(inferencer);
+    Ok(ReplState::new(inferencer))
+}
+
+fn run_scenario_rounds(
+    connection: &mut Connection,
+    scenario_name: &str,
+    test_scenario: &str,
+    convergence: &ConvergenceOptions,
+    repl: &mut ReplState,
+) -> Result<(Vec<MarginalTable>, ConvergenceReport), Box<dyn Error>> {
     let mut buffer = vec![];
     buffer.push(repl.inferencer.log_table_to_file()?);
-    let evidence_node = setup_test_scenario(connection, scenario_name, test_scenario, &mut repl)?;
-    if evidence_node.is_some() {
-        for _i in 0..50 {
-            repl.inferencer
-                .do_fan_out_from_node(connection, &evidence_node.clone().unwrap())?;
-            buffer.push(repl.inferencer.log_table_to_file()?);
+    let evidence_node = setup_test_scenario(connection, scenario_name, test_scenario, repl)?;
+    let (tables, report) =
+        repl.inferencer
+            .run_until_converged(connection, &evidence_node, convergence)?;
+    buffer.extend(tables);
+    Ok((buffer, report))
+}
+
+/// Runs belief propagation on `test_scenario` until it converges, see `ConvergenceOptions`.
+/// Returns the marginals before the evidence is set and after each sweep.
+pub fn run_inference_rounds(
+    connection: &mut Connection,
+    scenario_name: &str,
+    test_scenario: &str,
+    checkpoint: &Option<String>,
+    convergence: &ConvergenceOptions,
+) -> Result<(Vec<MarginalTable>, ConvergenceReport), Box<dyn Error>> {
+    let mut repl = setup_scenario_inferencer(connection, scenario_name, checkpoint)?;
+    run_scenario_rounds(connection, scenario_name, test_scenario, convergence, &mut repl)
+}
+
+/// Runs belief propagation on `test_scenario`, as `run_inference_rounds` does, and exact
+/// inference with the same evidence. Returns the exact marginals and how far the final belief
+/// propagation marginals are from them.
+pub fn compare_exact_inference(
+    connection: &mut Connection,
+    scenario_name: &str,
+    test_scenario: &str,
+    checkpoint: &Option<String>,
+    convergence: &ConvergenceOptions,
+) -> Result<(MarginalTable, MarginalComparison, ConvergenceReport), Box<dyn Error>> {
+    let mut repl = setup_scenario_inferencer(connection, scenario_name, checkpoint)?;
+    let (tables, report) =
+        run_scenario_rounds(connection, scenario_name, test_scenario, convergence, &mut repl)?;
+    let approximate = tables.last().ok_or("No belief propagation rounds were run")?;
+    let exact = run_exact_inference(connection, &repl.inferencer)?;
+    let comparison = compare_marginals(&exact, approximate)?;
+    Ok((exact, comparison, report))
+}
+
+/// Runs Gibbs sampling on `scenario_name` with the evidence of `test_scenario`.
+pub fn run_gibbs_inference(
+    connection: &mut Connection,
+    scenario_name: &str,
+    test_scenario: &str,
+    checkpoint: &Option<String>,
+    gibbs: &GibbsOptions,
+) -> Result<MarginalTable, Box<dyn Error>> {
+    let mut repl = setup_scenario_inferencer(connection, scenario_name, checkpoint)?;
+    setup_test_scenario(connection, scenario_name, test_scenario, &mut repl)?;
+    run_gibbs_sampling(connection, &repl.inferencer, gibbs)
+}
+
+/// Runs inference on the proposition graph of `target`, using every fact stored in
+/// `proposition_db` as evidence, except for the propositions in `excluded`, until `convergence`
+/// is met or its sweeps run out. The report says which.
+pub fn run_inference_with_stored_evidence(
+    connection: &mut Connection,
+    model: &Arc<InferenceModel>,
+    proposition_db: &Box<dyn BeliefTable>,
+    target: &Proposition,
+    excluded: &HashSet<Proposition>,
+    convergence: &ConvergenceOptions,
+) -> Result<(MarginalTable, ConvergenceReport), Box<dyn Error>> {
+    let proposition_graph = PropositionGraph::new_shared(connection, &model.graph, target.clone())?;
+    let fact_memory = HashMapBeliefTable::new();
+    for node in proposition_graph.get_bfs_order() {
+        if !node.is_single() {
+            continue;
         }
-    } else {
-        for _i in 0..50 {
-            repl.inferencer
-                .do_full_forward_and_backward(connection)?;
-            buffer.push(repl.inferencer.log_table_to_file()?);
+        let proposition = node.extract_single();
+        if excluded.contains(&proposition) {
+            continue;
         }
+        if let Some(probability) =
+            proposition_db.get_proposition_probability(connection, &proposition)?
+        {
+            fact_memory.store_proposition_probability(connection, &proposition, probability)?;
+        }
+    }
+    let mut inferencer =
+        Inferencer::new_mutable(model.clone(), proposition_graph.clone(), fact_memory)?;
+    inferencer.initialize_chart(connection)?;
+    let (_tables, report) = inferencer.run_until_converged(connection, &None, convergence)?;
+    if !report.converged {
+        trace!(
+            "run_inference_with_stored_evidence - {:?} did not converge: {:?}",
+            target,
+            &report
+        );
     }
-    Ok(buffer)
+    Ok((inferencer.build_marginal_table()?, report))
 }
@@ -107,6 +107,21 @@ pub struct HashMapBeliefTable {
     pi_messages: HashMap<(PropositionNode, PropositionNode, usize), f64>,
     lambda_messages: HashMap<(PropositionNode, PropositionNode, usize), f64>,
     bfs_order: Vec<PropositionNode>,
+    /// The largest change of a pi or lambda message since `reset_message_change`. A message set
+    /// for the first time counts as an infinite change.
+    max_message_change: f64,
+    /// The largest change of a message into each node since the node was last visited, for
+    /// residual scheduling.
+    residuals: HashMap<PropositionNode, f64>,
+    /// The weight of the old message when a message is set, 0 for no damping.
+    damping: f64,
+}
+
+fn message_change(old: Option<f64>, new: f64) -> f64 {
+    match old {
+        Some(old) => (new - old).abs(),
+        None => f64::INFINITY,
+    }
 }
 
 fn print_sorted_map(
@@ -184,9 +199,46 @@ impl HashMapBeliefTable {
             pi_messages: HashMap::new(),
             lambda_messages: HashMap::new(),
             bfs_order,
+            max_message_change: 0f64,
+            residuals: HashMap::new(),
+            damping: 0f64,
+        }
+    }
+
+    pub fn set_damping(&mut self, damping: f64) {
+        self.damping = damping;
+    }
+
+    /// Blends `value` with the message it replaces, if there is one, and records the change.
+    /// The change is that of the undamped message, so that damping does not make a sweep look
+    /// converged while the messages are still moving.
+    fn damp_and_record(&mut self, old: Option<f64>, to: &PropositionNode, value: f64) -> f64 {
+        let change = message_change(old, value);
+        self.max_message_change = self.max_message_change.max(change);
+        let residual = self.residuals.entry(to.clone()).or_insert(0f64);
+        *residual = residual.max(change);
+        match old {
+            Some(old) => self.damping * old + (1f64 - self.damping) * value,
+            None => value,
         }
     }
 
+    pub fn get_residual(&self, node: &PropositionNode) -> f64 {
+        self.residuals.get(node).copied().unwrap_or(0f64)
+    }
+
+    pub fn clear_residual(&mut self, node: &PropositionNode) {
+        self.residuals.insert(node.clone(), 0f64);
+    }
+
+    pub fn reset_message_change(&mut self) {
+        self.max_message_change = 0f64;
+    }
+
+    pub fn get_max_message_change(&self) -> f64 {
+        self.max_message_change
+    }
+
     // Getter for pi values
     pub fn get_pi_value(&self, node: &PropositionNode, outcome: usize) -> Option<f64> {
         let key = (node.clone(), outcome);
@@ -231,6 +283,8 @@ impl HashMapBeliefTable {
         value: f64,
     ) {
         let key = (from.clone(), to.clone(), outcome);
+        let old = self.pi_messages.get(&key).cloned();
+        let value = self.damp_and_record(old, to, value);
         self.pi_messages.insert(key, value);
     }
 
@@ -254,6 +308,8 @@ impl HashMapBeliefTable {
         value: f64,
     ) {
         let key = (from.clone(), to.clone(), outcome);
+        let old = self.lambda_messages.get(&key).cloned();
+        let value = self.damp_and_record(old, to, value);
         self.lambda_messages.insert(key, value);
     }
 }
@@ -125,6 +125,18 @@ pub fn extract_backimplications_from_proposition(
     Ok(backimplications)
 }
 
+/// Whether a proposition with these backimplications is a root of the proposition graph: it has
+/// none, or only the existence factor, which tells nothing beyond the base rate of the relation.
+pub fn is_root_backimplications(factors: &[PropositionFactor]) -> bool {
+    factors.iter().all(|factor| {
+        factor
+            .premise
+            .terms
+            .iter()
+            .all(|term| term.predicate.relation.relation_name == existence_predicate_name())
+    })
+}
+
 pub fn extract_existence_factor_for_predicate(
     conclusion: &Predicate,
 ) -> Result<ImplicationFactor, Box<dyn Error>> {
@@ -0,0 +1,37 @@
+use crate::common::setup::CommandLineOptions;
+
+/// Penalties and clipping applied to the weight updates of the `ExponentialModel`.
+#[derive(Clone, Debug)]
+pub struct RegularizationConfig {
+    pub l1_penalty: f64,
+    pub l2_penalty: f64,
+    pub gradient_clip: Option<f64>,
+    pub weight_clip: Option<f64>,
+}
+
+impl RegularizationConfig {
+    pub fn new(options: &CommandLineOptions) -> RegularizationConfig {
+        RegularizationConfig {
+            l1_penalty: options.l1_penalty,
+            l2_penalty: options.l2_penalty,
+            gradient_clip: options.gradient_clip,
+            weight_clip: options.weight_clip,
+        }
+    }
+
+    pub fn none() -> RegularizationConfig {
+        RegularizationConfig {
+            l1_penalty: 0f64,
+            l2_penalty: 0f64,
+            gradient_clip: None,
+            weight_clip: None,
+        }
+    }
+}
+
+pub fn clip(value: f64, bound: Option<f64>) -> f64 {
+    match bound {
+        Some(bound) => value.clamp(-bound, bound),
+        None => value,
+    }
+}
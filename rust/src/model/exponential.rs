@@ -1,10 +1,21 @@
 use super::choose::extract_backimplications_from_proposition;
+use super::config::{clip, RegularizationConfig};
+use super::frozen::get_frozen_weights;
+use super::interaction::{
+    add_interaction_features, get_interaction_order, record_interactions, store_interaction_order,
+    NO_INTERACTIONS,
+};
 use super::objects::ImplicationFactor;
-use super::weights::{negative_feature, positive_feature, ExponentialWeights};
+use super::optimizer::{Optimizer, OptimizerFactory, SgdOptimizer};
+use super::weights::{
+    bias_feature, bias_features, bias_keys, implication_features, negative_feature, positive_feature,
+    ExponentialWeights,
+};
 use crate::common::interface::{BeliefTable, PredictStatistics, TrainStatistics};
+use crate::common::checkpoint::warm_start_namespace;
 use crate::common::model::InferenceModel;
 use crate::common::model::{FactorContext, FactorModel};
-use crate::common::redis::RedisManager;
+use crate::common::redis::{get_value, key_exists, set_value, RedisManager};
 use crate::common::resources::ResourceContext;
 use crate::common::setup::CommandLineOptions;
 use crate::model::objects::Predicate;
@@ -12,36 +23,140 @@ use crate::model::weights::CLASS_LABELS;
 use crate::{print_blue, print_yellow};
 use redis::Connection;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::error::Error;
 use std::rc::Rc;
 use std::sync::Arc;
 pub struct ExponentialModel {
+    namespace: String,
     print_training_loss: bool,
+    /// The largest number of backimplications in one interaction feature.
+    interaction_order: usize,
+    /// Whether each conclusion relation also gets a bias per domain signature.
+    domain_bias: bool,
+    /// Whether training continues from the stored weights, in which case the shared bias weights
+    /// and their optimizer state are kept.
+    resume_training: bool,
     weights: ExponentialWeights,
+    /// The fixed weight of each feature of a frozen implication, which training leaves alone.
+    frozen_weights: HashMap<String, f64>,
+    optimizer: Box<dyn Optimizer>,
+    regularization: RegularizationConfig,
+}
+
+const DEFAULT_LEARNING_RATE: f64 = 0.05;
+
+pub const DOMAIN_BIAS_KEY: &str = "domain_bias";
+
+fn store_domain_bias(
+    connection: &mut Connection,
+    namespace: &str,
+    domain_bias: bool,
+) -> Result<(), Box<dyn Error>> {
+    set_value(connection, namespace, DOMAIN_BIAS_KEY, &domain_bias.to_string())
+}
+
+pub fn get_domain_bias(connection: &mut Connection, namespace: &str) -> Result<bool, Box<dyn Error>> {
+    match get_value(connection, namespace, DOMAIN_BIAS_KEY)? {
+        Some(record) => Ok(record.parse::<bool>()?),
+        None => Ok(false),
+    }
 }
 
 impl ExponentialModel {
-    pub fn new_mutable(namespace: String) -> Result<Box<dyn FactorModel>, Box<dyn Error>> {
-        let weights = ExponentialWeights::new(namespace.clone())?;
+    /// Stores the settings that change the feature set, so that `new_shared` builds the same
+    /// features for inference.
+    fn new_mutable_with_order(
+        connection: &mut Connection,
+        namespace: String,
+        options: &CommandLineOptions,
+        interaction_order: usize,
+    ) -> Result<Box<dyn FactorModel>, Box<dyn Error>> {
+        store_interaction_order(connection, &namespace, interaction_order)?;
+        store_domain_bias(connection, &namespace, options.domain_bias)?;
+        let warm_start = warm_start_namespace(options);
+        if let Some(source) = &warm_start {
+            // Otherwise a mistyped namespace or checkpoint would silently train from random
+            // weights.
+            if !key_exists(connection, source, ExponentialWeights::WEIGHTS_KEY)? {
+                return Err(format!("No weights to warm start from in {}", source).into());
+            }
+        }
+        let weights = ExponentialWeights::new_with_warm_start(namespace.clone(), warm_start)?;
+        let frozen_weights = get_frozen_weights(connection, &namespace)?;
+        let optimizer = OptimizerFactory::new_mutable(&namespace, options)?;
         Ok(Box::new(ExponentialModel {
-            print_training_loss: false,
+            namespace,
+            print_training_loss: options.print_training_loss,
+            interaction_order,
+            domain_bias: options.domain_bias,
+            resume_training: options.resume_training || options.incremental,
             weights,
+            frozen_weights,
+            optimizer,
+            regularization: RegularizationConfig::new(options),
         }))
     }
-    pub fn new_shared(namespace: String) -> Result<Arc<dyn FactorModel>, Box<dyn Error>> {
+
+    pub fn new_mutable(
+        connection: &mut Connection,
+        namespace: String,
+        options: &CommandLineOptions,
+    ) -> Result<Box<dyn FactorModel>, Box<dyn Error>> {
+        Self::new_mutable_with_order(connection, namespace, options, NO_INTERACTIONS)
+    }
+
+    /// The exponential model, plus features for each set of up to `--interaction_order`
+    /// backimplications that fire for the same conclusion.
+    pub fn new_mutable_with_interactions(
+        connection: &mut Connection,
+        namespace: String,
+        options: &CommandLineOptions,
+    ) -> Result<Box<dyn FactorModel>, Box<dyn Error>> {
+        let interaction_order = options.interaction_order;
+        Self::new_mutable_with_order(connection, namespace, options, interaction_order)
+    }
+
+    /// Reads the feature settings that the namespace was trained with.
+    pub fn new_shared(
+        connection: &mut Connection,
+        namespace: String,
+    ) -> Result<Arc<dyn FactorModel>, Box<dyn Error>> {
+        let interaction_order = get_interaction_order(connection, &namespace)?;
+        let domain_bias = get_domain_bias(connection, &namespace)?;
         let weights = ExponentialWeights::new(namespace.clone())?;
         Ok(Arc::new(ExponentialModel {
+            namespace,
             print_training_loss: false,
+            interaction_order,
+            domain_bias,
+            resume_training: false,
             weights,
+            frozen_weights: HashMap::new(),
+            optimizer: Box::new(SgdOptimizer::new(DEFAULT_LEARNING_RATE)),
+            regularization: RegularizationConfig::none(),
         }))
     }
+
+    fn features_from_factor(
+        &self,
+        factor: &FactorContext,
+    ) -> Result<Vec<BTreeMap<String, f64>>, Box<dyn Error>> {
+        let mut features = features_from_factor(factor)?;
+        add_bias_features(&mut features, factor, self.domain_bias);
+        if self.interaction_order > NO_INTERACTIONS {
+            add_interaction_features(&mut features, factor, self.interaction_order);
+        }
+        Ok(features)
+    }
 }
 
-fn dot_product(dict1: &HashMap<String, f64>, dict2: &HashMap<String, f64>) -> f64 {
+/// Sums over `features` in key order, which the `BTreeMap` keeps, so that the floating point
+/// result is the same on every run without sorting in the training loop.
+fn dot_product(weights: &HashMap<String, f64>, features: &BTreeMap<String, f64>) -> f64 {
     let mut result = 0.0;
-    for (key, &v1) in dict1 {
-        if let Some(&v2) = dict2.get(key) {
+    for (key, &v2) in features {
+        if let Some(&v1) = weights.get(key) {
             let product = v1 * v2;
             trace!(
                 "dot_product: key {}, v1 {}, v2 {}, product {}",
@@ -57,17 +172,62 @@ fn dot_product(dict1: &HashMap<String, f64>, dict2: &HashMap<String, f64>) -> f6
     result
 }
 
-pub fn compute_potential(weights: &HashMap<String, f64>, features: &HashMap<String, f64>) -> f64 {
+pub fn compute_potential(weights: &HashMap<String, f64>, features: &BTreeMap<String, f64>) -> f64 {
     let dot = dot_product(weights, features);
     dot.exp()
 }
 
+/// The log of `compute_potential`, which does not overflow for large dot products.
+pub fn compute_log_potential(
+    weights: &HashMap<String, f64>,
+    features: &BTreeMap<String, f64>,
+) -> f64 {
+    dot_product(weights, features)
+}
+
+pub fn log_sum_exp(values: &[f64]) -> f64 {
+    let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    if max.is_infinite() {
+        return max;
+    }
+    let sum: f64 = values.iter().map(|v| (v - max).exp()).sum();
+    max + sum.ln()
+}
+
+/// Normalizes the log potentials of each class into class probabilities.
+pub fn normalize_log_potentials(log_potentials: &[f64]) -> Vec<f64> {
+    // Infinite potentials can't be subtracted from each other, so the classes with the largest
+    // potential share the probability when it is +inf, and every class does when all are -inf.
+    let max = log_potentials
+        .iter()
+        .cloned()
+        .fold(f64::NEG_INFINITY, f64::max);
+    if max.is_infinite() {
+        let num_max = log_potentials.iter().filter(|v| **v == max).count();
+        return log_potentials
+            .iter()
+            .map(|v| {
+                if *v == max {
+                    1f64 / num_max as f64
+                } else {
+                    0f64
+                }
+            })
+            .collect();
+    }
+    let log_normalization = log_sum_exp(log_potentials);
+    log_potentials
+        .iter()
+        .map(|v| (v - log_normalization).exp())
+        .collect()
+}
+
 pub fn features_from_factor(
     factor: &FactorContext,
-) -> Result<Vec<HashMap<String, f64>>, Box<dyn Error>> {
+) -> Result<Vec<BTreeMap<String, f64>>, Box<dyn Error>> {
     let mut vec_result = vec![];
     for class_label in CLASS_LABELS {
-        let mut result = HashMap::new();
+        let mut result = BTreeMap::new();
         for (i, premise) in factor.factor.iter().enumerate() {
             debug!("Processing backimplication {}", i);
             let feature = premise.inference.unique_key();
@@ -92,9 +252,27 @@ pub fn features_from_factor(
     Ok(vec_result)
 }
 
+/// A bias feature, always 1, for the relation of the conclusion, and optionally for its domain
+/// signature. The conclusion is read off the backimplications, which all share it.
+pub fn add_bias_features(
+    features: &mut [BTreeMap<String, f64>],
+    factor: &FactorContext,
+    domain_bias: bool,
+) {
+    let conclusion = match factor.factor.first() {
+        Some(premise) => &premise.conclusion.predicate,
+        None => return,
+    };
+    for class_label in CLASS_LABELS {
+        for key in bias_keys(conclusion, domain_bias) {
+            features[class_label].insert(bias_feature(&key, class_label), 1f64);
+        }
+    }
+}
+
 pub fn compute_expected_features(
     probability: f64,
-    features: &HashMap<String, f64>,
+    features: &BTreeMap<String, f64>,
 ) -> HashMap<String, f64> {
     let mut result = HashMap::new();
     for (key, &value) in features {
@@ -103,34 +281,35 @@ pub fn compute_expected_features(
     result
 }
 
-const LEARNING_RATE: f64 = 0.05;
+/// The subgradient of |w| that is 0 at 0, so that the L1 penalty leaves weights that are exactly
+/// zero alone instead of pushing them back and forth across 0. `f64::signum` is 1 at 0.
+fn l1_subgradient(weight: f64) -> f64 {
+    if weight > 0f64 {
+        1f64
+    } else if weight < 0f64 {
+        -1f64
+    } else {
+        0f64
+    }
+}
 
-pub fn do_sgd_update(
+/// The gradient of the penalized log-likelihood, clipped if configured.
+pub fn compute_gradient(
     weights: &HashMap<String, f64>,
     gold_features: &HashMap<String, f64>,
     expected_features: &HashMap<String, f64>,
-    print_training_loss: bool,
+    regularization: &RegularizationConfig,
 ) -> HashMap<String, f64> {
-    let mut new_weights = HashMap::new();
+    let mut gradient = HashMap::new();
     for (feature, &wv) in weights {
         let gv = gold_features.get(feature).unwrap_or(&0.0);
         let ev = expected_features.get(feature).unwrap_or(&0.0);
-        let new_weight = wv + LEARNING_RATE * (gv - ev);
-        let loss = (gv - ev).abs();
-        if print_training_loss {
-            trace!(
-                "feature: {}, gv: {}, ev: {}, loss: {}, old_weight: {}, new_weight: {}",
-                feature,
-                gv,
-                ev,
-                loss,
-                wv,
-                new_weight
-            );
-        }
-        new_weights.insert(feature.clone(), new_weight);
+        let penalty =
+            regularization.l2_penalty * wv + regularization.l1_penalty * l1_subgradient(wv);
+        let raw = gv - ev - penalty;
+        gradient.insert(feature.clone(), clip(raw, regularization.gradient_clip));
     }
-    new_weights
+    gradient
 }
 
 impl FactorModel for ExponentialModel {
@@ -139,18 +318,48 @@ impl FactorModel for ExponentialModel {
         connection: &mut Connection,
         implication: &ImplicationFactor,
     ) -> Result<(), Box<dyn Error>> {
-        self.weights.initialize_weights(connection, implication)?;
+        self.weights.initialize_weights(
+            connection,
+            implication,
+            self.domain_bias,
+            !self.resume_training,
+        )?;
+        let fixed: HashMap<String, f64> = implication_features(implication)
+            .into_iter()
+            .filter_map(|feature| {
+                let weight = self.frozen_weights.get(&feature).copied()?;
+                Some((feature, weight))
+            })
+            .collect();
+        if !fixed.is_empty() {
+            trace!("initialize_connection - frozen {:?}", &fixed);
+            self.weights.save_weight_vector(connection, &fixed)?;
+        }
+        let mut reset = implication_features(implication);
+        if !self.resume_training {
+            reset.extend(bias_features(&implication.conclusion, self.domain_bias));
+        }
+        self.optimizer.reset_features(connection, &reset)?;
         Ok(())
     }
 
+    fn is_connection_initialized(
+        &self,
+        connection: &mut Connection,
+        implication: &ImplicationFactor,
+    ) -> Result<bool, Box<dyn Error>> {
+        self.weights.has_weights(connection, implication)
+    }
+
     fn train(
         &mut self,
         connection: &mut Connection,
         factor: &FactorContext,
         gold_probability: f64,
+        example_weight: f64,
     ) -> Result<TrainStatistics, Box<dyn Error>> {
         trace!("train_on_example - Getting features from backimplications");
-        let features = match features_from_factor(factor) {
+        let features = match self.features_from_factor(factor) {
             Ok(f) => f,
             Err(e) => {
                 trace!(
@@ -161,7 +370,7 @@ impl FactorModel for ExponentialModel {
             }
         };
         let mut weight_vectors = vec![];
-        let mut potentials = vec![];
+        let mut log_potentials = vec![];
         for class_label in CLASS_LABELS {
             for (feature, weight) in &features[class_label] {
                 trace!("feature {:?} {}", feature, weight);
@@ -181,41 +390,73 @@ impl FactorModel for ExponentialModel {
                 }
             };
             trace!("train_on_example - Computing probability");
-            let potential = compute_potential(&weight_vector, &features[class_label]);
-            trace!("train_on_example - Computed probability: {}", potential);
-            potentials.push(potential);
+            let log_potential = compute_log_potential(&weight_vector, &features[class_label]);
+            trace!("train_on_example - Computed log potential: {}", log_potential);
+            log_potentials.push(log_potential);
             weight_vectors.push(weight_vector);
         }
-        let normalization = potentials[0] + potentials[1];
+        let probabilities = normalize_log_potentials(&log_potentials);
+        if self.interaction_order > NO_INTERACTIONS {
+            record_interactions(connection, &self.namespace, factor, self.interaction_order)?;
+        }
         for class_label in CLASS_LABELS {
-            let probability = potentials[class_label] / normalization;
+            let probability = probabilities[class_label];
             trace!("train_on_example - Computing expected features");
             let this_true_prob = if class_label == 0 {
                 1f64 - gold_probability
             } else {
                 gold_probability
             };
-            let gold = compute_expected_features(this_true_prob, &features[class_label]);
-            let expected = compute_expected_features(probability, &features[class_label]);
-            trace!("train_on_example - Performing SGD update");
-            let new_weight = do_sgd_update(
-                &weight_vectors[class_label],
-                &gold,
-                &expected,
-                self.print_training_loss,
+            // The example weight scales the data term of the gradient, but not the penalty.
+            let gold = compute_expected_features(
+                example_weight * this_true_prob,
+                &features[class_label],
             );
+            let expected =
+                compute_expected_features(example_weight * probability, &features[class_label]);
+            trace!("train_on_example - Performing optimizer update");
+            let trainable: HashMap<String, f64> = weight_vectors[class_label]
+                .iter()
+                .filter(|(feature, _)| !self.frozen_weights.contains_key(*feature))
+                .map(|(feature, weight)| (feature.clone(), *weight))
+                .collect();
+            let gradient =
+                compute_gradient(&trainable, &gold, &expected, &self.regularization);
+            let mut new_weight = self.optimizer.update(connection, &trainable, &gradient)?;
+            for weight in new_weight.values_mut() {
+                *weight = clip(*weight, self.regularization.weight_clip);
+            }
+            if self.print_training_loss {
+                for (feature, g) in &gradient {
+                    trace!(
+                        "feature: {}, gradient: {}, old_weight: {}, new_weight: {}",
+                        feature,
+                        g,
+                        weight_vectors[class_label][feature],
+                        new_weight[feature]
+                    );
+                }
+            }
             trace!("train_on_example - Saving new weights");
             self.weights.save_weight_vector(connection, &new_weight)?;
         }
+        let statistics = TrainStatistics::new(probabilities[1], gold_probability);
+        if self.print_training_loss {
+            trace!(
+                "train_on_example - log_loss: {}, squared_error: {}",
+                statistics.log_loss,
+                statistics.squared_error
+            );
+        }
         trace!("train_on_example - End");
-        Ok(TrainStatistics { loss: 1f64 })
+        Ok(statistics)
     }
     fn predict(
         &self,
         connection: &mut Connection,
         factor: &FactorContext,
     ) -> Result<PredictStatistics, Box<dyn Error>> {
-        let features = match features_from_factor(factor) {
+        let features = match self.features_from_factor(factor) {
             Ok(f) => f,
             Err(e) => {
                 trace!(
@@ -225,7 +466,7 @@ impl FactorModel for ExponentialModel {
                 return Err(e);
             }
         };
-        let mut potentials = vec![];
+        let mut log_potentials = vec![];
         for class_label in CLASS_LABELS {
             let this_features = &features[class_label];
             for (feature, weight) in this_features.iter() {
@@ -245,15 +486,14 @@ impl FactorModel for ExponentialModel {
             for (feature, weight) in weight_vector.iter() {
                 trace!("weight {:?} {}", &feature, weight);
             }
-            let potential = compute_potential(&weight_vector, &this_features);
-            trace!("potential for {} {} {:?}", class_label, potential, &factor);
-            potentials.push(potential);
+            let log_potential = compute_log_potential(&weight_vector, &this_features);
+            trace!("log potential for {} {} {:?}", class_label, log_potential, &factor);
+            log_potentials.push(log_potential);
         }
-        let normalization = potentials[0] + potentials[1];
-        let probability = potentials[1] / normalization;
+        let probability = normalize_log_potentials(&log_potentials)[1];
         trace!(
-            "dot_product: normalization {}, marginal {}",
-            normalization,
+            "dot_product: log potentials {:?}, marginal {}",
+            log_potentials,
             probability
         );
         Ok(PredictStatistics { probability })
@@ -0,0 +1,66 @@
+use std::{error::Error, sync::Arc};
+
+use redis::Connection;
+
+use crate::baseline::model::MonolithicBayes;
+use crate::common::{
+    model::FactorModel,
+    redis::{get_value, set_value},
+    setup::CommandLineOptions,
+};
+
+use super::{exponential::ExponentialModel, noisy_or::NoisyOrModel};
+
+pub struct FactorModelFactory;
+
+impl FactorModelFactory {
+    pub const FACTOR_MODEL_KEY: &'static str = "factor_model";
+    pub const DEFAULT_FACTOR_MODEL: &'static str = "exponential";
+
+    /// Creates the model named by `--factor_model`, for training.
+    pub fn new_mutable(
+        connection: &mut Connection,
+        namespace: String,
+        options: &CommandLineOptions,
+    ) -> Result<Box<dyn FactorModel>, Box<dyn Error>> {
+        match options.factor_model.as_str() {
+            "exponential" => ExponentialModel::new_mutable(connection, namespace, options),
+            "interaction" => {
+                ExponentialModel::new_mutable_with_interactions(connection, namespace, options)
+            }
+            "noisy_or" => NoisyOrModel::new_mutable(connection, namespace, options),
+            "monolithic" => MonolithicBayes::new_mutable(namespace),
+            name => Err(format!("Unknown FactorModel type {}", name).into()),
+        }
+    }
+
+    /// Creates the model that the namespace was trained with, for inference.
+    pub fn new_shared(
+        connection: &mut Connection,
+        namespace: String,
+    ) -> Result<Arc<dyn FactorModel>, Box<dyn Error>> {
+        let model_name = Self::get_model_name(connection, &namespace)?;
+        match model_name.as_str() {
+            "exponential" | "interaction" => ExponentialModel::new_shared(connection, namespace),
+            "noisy_or" => NoisyOrModel::new_shared(namespace),
+            "monolithic" => MonolithicBayes::new_shared(namespace),
+            name => Err(format!("Unknown FactorModel type {}", name).into()),
+        }
+    }
+
+    pub fn store_model_name(
+        connection: &mut Connection,
+        namespace: &str,
+        model_name: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        set_value(connection, namespace, Self::FACTOR_MODEL_KEY, model_name)
+    }
+
+    pub fn get_model_name(
+        connection: &mut Connection,
+        namespace: &str,
+    ) -> Result<String, Box<dyn Error>> {
+        let stored = get_value(connection, namespace, Self::FACTOR_MODEL_KEY)?;
+        Ok(stored.unwrap_or(Self::DEFAULT_FACTOR_MODEL.to_string()))
+    }
+}
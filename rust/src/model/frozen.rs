@@ -0,0 +1,135 @@
+use std::{collections::HashMap, error::Error};
+
+use redis::Connection;
+use serde::{Deserialize, Serialize};
+
+use crate::common::redis::{map_delete, map_get_all, map_insert};
+
+use super::weights::{negative_feature, positive_feature, CLASS_LABELS};
+
+/// The map from `ImplicationFactor::unique_key` to the value it is frozen at.
+pub const FROZEN_KEY: &str = "frozen_implications";
+
+/// The value of a frozen implication, which training does not change.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum FrozenValue {
+    /// The exponential weights of the implication, in the order `+>-`, `->-`, `+>+`, `->+`. Only
+    /// the exponential models can use them.
+    Weights(Vec<f64>),
+    /// The noisy-or activation, i.e., P(conclusion | premise) when this implication is the only
+    /// one that fires and the leak is 0. Only the noisy-or model can use it: in the exponential
+    /// model the biases and the other implications also move the conclusion, so no fixed weights
+    /// give this probability.
+    Probability(f64),
+}
+
+impl FrozenValue {
+    /// The exponential weights that realize the value, by feature.
+    pub fn exponential_weights(
+        &self,
+        implication_key: &str,
+    ) -> Result<HashMap<String, f64>, Box<dyn Error>> {
+        let mut result = HashMap::new();
+        match self {
+            FrozenValue::Weights(weights) => {
+                if weights.len() != 2 * CLASS_LABELS.len() {
+                    return Err(format!(
+                        "Frozen weights for {} need 4 values, got {}",
+                        implication_key,
+                        weights.len()
+                    )
+                    .into());
+                }
+                for class_label in CLASS_LABELS {
+                    result.insert(
+                        positive_feature(implication_key, class_label),
+                        weights[2 * class_label],
+                    );
+                    result.insert(
+                        negative_feature(implication_key, class_label),
+                        weights[2 * class_label + 1],
+                    );
+                }
+            }
+            FrozenValue::Probability(_) => {
+                return Err(format!(
+                    "{} is frozen at a probability, which only a noisy-or model can use; freeze it at weights for an exponential model",
+                    implication_key
+                )
+                .into())
+            }
+        }
+        Ok(result)
+    }
+
+    /// The noisy-or activation probability that realizes the value.
+    pub fn activation(&self, implication_key: &str) -> Result<f64, Box<dyn Error>> {
+        match self {
+            FrozenValue::Probability(probability) => {
+                if !(0f64..=1f64).contains(probability) {
+                    return Err(format!(
+                        "Frozen probability for {} is out of range: {}",
+                        implication_key, probability
+                    )
+                    .into());
+                }
+                Ok(*probability)
+            }
+            FrozenValue::Weights(_) => Err(format!(
+                "{} is frozen at exponential weights, which a noisy-or model can't use",
+                implication_key
+            )
+            .into()),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            FrozenValue::Weights(weights) => format!("fixed weights {:?}", weights),
+            FrozenValue::Probability(probability) => {
+                format!("fixed noisy-or activation {}", probability)
+            }
+        }
+    }
+}
+
+pub fn freeze_implication(
+    connection: &mut Connection,
+    namespace: &str,
+    implication_key: &str,
+    value: &FrozenValue,
+) -> Result<(), Box<dyn Error>> {
+    let record = serde_json::to_string(value)?;
+    map_insert(connection, namespace, FROZEN_KEY, implication_key, &record)
+}
+
+pub fn unfreeze_implication(
+    connection: &mut Connection,
+    namespace: &str,
+    implication_key: &str,
+) -> Result<(), Box<dyn Error>> {
+    map_delete(connection, namespace, FROZEN_KEY, implication_key)
+}
+
+pub fn get_frozen_implications(
+    connection: &mut Connection,
+    namespace: &str,
+) -> Result<HashMap<String, FrozenValue>, Box<dyn Error>> {
+    let mut result = HashMap::new();
+    for (implication_key, record) in map_get_all(connection, namespace, FROZEN_KEY)? {
+        result.insert(implication_key, serde_json::from_str(&record)?);
+    }
+    Ok(result)
+}
+
+/// The fixed weight of every feature of every frozen implication.
+pub fn get_frozen_weights(
+    connection: &mut Connection,
+    namespace: &str,
+) -> Result<HashMap<String, f64>, Box<dyn Error>> {
+    let mut result = HashMap::new();
+    for (implication_key, value) in get_frozen_implications(connection, namespace)? {
+        result.extend(value.exponential_weights(&implication_key)?);
+    }
+    Ok(result)
+}
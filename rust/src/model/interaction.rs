@@ -0,0 +1,144 @@
+use super::weights::{
+    interaction_feature, is_interaction_feature, ExponentialWeights, CLASS_LABELS,
+};
+use crate::common::{
+    model::FactorContext,
+    redis::{
+        get_value, key_delete, map_delete, map_get_all, namespace_keys, set_add, set_members,
+        set_value,
+    },
+};
+use redis::Connection;
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+};
+
+pub const INTERACTION_ORDER_KEY: &str = "interaction_order";
+pub const INTERACTIONS_KEY: &str = "interactions";
+
+/// An order of 1 means no interaction features, i.e., the plain exponential model.
+pub const NO_INTERACTIONS: usize = 1;
+
+fn collect_subsets(
+    size: usize,
+    start: usize,
+    total: usize,
+    current: &mut Vec<usize>,
+    result: &mut Vec<Vec<usize>>,
+) {
+    if current.len() == size {
+        result.push(current.clone());
+        return;
+    }
+    for index in start..total {
+        current.push(index);
+        collect_subsets(size, index + 1, total, current, result);
+        current.pop();
+    }
+}
+
+/// All sets of between 2 and `order` premise indices, out of `total` premises.
+fn interaction_subsets(total: usize, order: usize) -> Vec<Vec<usize>> {
+    let mut result = vec![];
+    for size in 2..=order.min(total) {
+        collect_subsets(size, 0, total, &mut vec![], &mut result);
+    }
+    result
+}
+
+/// The implication keys of the subset, sorted so that the key does not depend on the order in
+/// which the backimplications were found.
+fn interaction_key(factor: &FactorContext, subset: &[usize]) -> String {
+    let mut keys: Vec<String> = subset
+        .iter()
+        .map(|&i| factor.factor[i].inference.unique_key())
+        .collect();
+    keys.sort();
+    keys.join(" & ")
+}
+
+/// The interaction of a set of backimplications for the same conclusion fires with the
+/// probability that all of their premises are true, taken as the product.
+pub fn interaction_values(factor: &FactorContext, order: usize) -> HashMap<String, f64> {
+    let mut result = HashMap::new();
+    for subset in interaction_subsets(factor.factor.len(), order) {
+        let key = interaction_key(factor, &subset);
+        let value: f64 = subset.iter().map(|&i| factor.probabilities[i]).product();
+        result.insert(key, value);
+    }
+    result
+}
+
+/// Adds one feature per interaction and class label to the features from `features_from_factor`.
+pub fn add_interaction_features(
+    features: &mut [BTreeMap<String, f64>],
+    factor: &FactorContext,
+    order: usize,
+) {
+    let values = interaction_values(factor, order);
+    for class_label in CLASS_LABELS {
+        for (key, value) in &values {
+            features[class_label].insert(interaction_feature(key, class_label), *value);
+        }
+    }
+}
+
+pub fn store_interaction_order(
+    connection: &mut Connection,
+    namespace: &str,
+    order: usize,
+) -> Result<(), Box<dyn Error>> {
+    set_value(connection, namespace, INTERACTION_ORDER_KEY, &order.to_string())
+}
+
+pub fn get_interaction_order(
+    connection: &mut Connection,
+    namespace: &str,
+) -> Result<usize, Box<dyn Error>> {
+    match get_value(connection, namespace, INTERACTION_ORDER_KEY)? {
+        Some(record) => Ok(record.parse::<usize>()?),
+        None => Ok(NO_INTERACTIONS),
+    }
+}
+
+/// Remembers the interactions seen in training, so that the explorer can list them.
+pub fn record_interactions(
+    connection: &mut Connection,
+    namespace: &str,
+    factor: &FactorContext,
+    order: usize,
+) -> Result<(), Box<dyn Error>> {
+    for key in interaction_values(factor, order).keys() {
+        set_add(connection, namespace, INTERACTIONS_KEY, key)?;
+    }
+    Ok(())
+}
+
+pub fn get_recorded_interactions(
+    connection: &mut Connection,
+    namespace: &str,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut result = set_members(connection, namespace, INTERACTIONS_KEY)?;
+    result.sort();
+    Ok(result)
+}
+
+/// Deletes every interaction weight, its optimizer state and the recorded interactions. They are
+/// created lazily, the first time an interaction is seen, so training from scratch would
+/// otherwise start from the interaction weights of the last run.
+pub fn clear_interactions(
+    connection: &mut Connection,
+    namespace: &str,
+) -> Result<(), Box<dyn Error>> {
+    let mut keys = vec![ExponentialWeights::WEIGHTS_KEY.to_string()];
+    keys.extend(namespace_keys(connection, namespace, "optimizer:*")?);
+    for key in &keys {
+        for feature in map_get_all(connection, namespace, key)?.keys() {
+            if is_interaction_feature(feature) {
+                map_delete(connection, namespace, key, feature)?;
+            }
+        }
+    }
+    key_delete(connection, namespace, INTERACTIONS_KEY)
+}
@@ -4,4 +4,9 @@ pub mod choose;
 pub mod ops;
 pub mod weights;
 pub mod exponential;
+pub mod optimizer;
+pub mod noisy_or;
+pub mod factory;
+pub mod interaction;
+pub mod frozen;
 pub mod config;
\ No newline at end of file
@@ -0,0 +1,295 @@
+use super::frozen::get_frozen_implications;
+use super::objects::ImplicationFactor;
+use super::optimizer::{Optimizer, OptimizerFactory, SgdOptimizer};
+use crate::common::interface::{PredictStatistics, TrainStatistics};
+use crate::common::model::{FactorContext, FactorModel};
+use crate::common::random::keyed_rng;
+use crate::common::redis::{map_get, map_insert};
+use crate::common::setup::CommandLineOptions;
+use rand::Rng;
+use redis::Connection;
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::Arc;
+
+const MIN_PROBABILITY: f64 = 1e-6;
+const INITIAL_LEAK: f64 = 0.05;
+const DEFAULT_LEARNING_RATE: f64 = 0.05;
+
+fn sigmoid(x: f64) -> f64 {
+    1f64 / (1f64 + (-x).exp())
+}
+
+pub fn logit(p: f64) -> f64 {
+    let p = p.clamp(MIN_PROBABILITY, 1f64 - MIN_PROBABILITY);
+    (p / (1f64 - p)).ln()
+}
+
+pub fn activation_feature(implication_key: &str) -> String {
+    format!("activation {}", implication_key)
+}
+
+/// The leak of the conclusions of `relation_name`, so that each relation has its own base rate.
+pub fn leak_feature(relation_name: &str) -> String {
+    format!("leak {}", relation_name)
+}
+
+/// The leak shared by every relation, in namespaces trained before leaks were per relation.
+const LEGACY_LEAK_FEATURE: &str = "leak";
+
+/// The relation of the conclusion, read off the backimplications, which all share it.
+fn conclusion_relation(factor: &FactorContext) -> Result<String, Box<dyn Error>> {
+    let premise = factor
+        .factor
+        .first()
+        .ok_or("A noisy-or factor needs at least one backimplication")?;
+    Ok(premise.conclusion.predicate.relation.relation_name.clone())
+}
+
+/// Activation probabilities, one per implication, and the leak probability of each conclusion
+/// relation, stored as probabilities in the "noisy_or" hash of the namespace.
+pub struct NoisyOrParameters {
+    namespace: String,
+}
+
+impl NoisyOrParameters {
+    pub const PARAMETERS_KEY: &'static str = "noisy_or";
+
+    pub fn new(namespace: String) -> Result<NoisyOrParameters, Box<dyn Error>> {
+        Ok(NoisyOrParameters { namespace })
+    }
+
+    pub fn read_probability(
+        &self,
+        connection: &mut Connection,
+        feature: &str,
+    ) -> Result<Option<f64>, Box<dyn Error>> {
+        let record = map_get(connection, &self.namespace, Self::PARAMETERS_KEY, feature)?;
+        match record {
+            Some(record) => Ok(Some(
+                record
+                    .parse::<f64>()
+                    .map_err(|e| Box::new(e) as Box<dyn Error>)?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    pub fn save_probability(
+        &mut self,
+        connection: &mut Connection,
+        feature: &str,
+        probability: f64,
+    ) -> Result<(), Box<dyn Error>> {
+        let clamped = probability.clamp(MIN_PROBABILITY, 1f64 - MIN_PROBABILITY);
+        map_insert(
+            connection,
+            &self.namespace,
+            Self::PARAMETERS_KEY,
+            feature,
+            &clamped.to_string(),
+        )
+    }
+}
+
+/// P(conclusion) = 1 - (1 - leak) * prod_i (1 - activation_i * x_i), where x_i is the probability
+/// that the premise of implication i is true. Linear in the fan-in, unlike the exponential model.
+pub struct NoisyOrModel {
+    print_training_loss: bool,
+    parameters: NoisyOrParameters,
+    /// The fixed activation of each frozen implication, by activation feature.
+    frozen_activations: HashMap<String, f64>,
+    /// Whether training continues from the stored parameters, in which case the shared leaks and
+    /// their optimizer state are kept.
+    resume_training: bool,
+    optimizer: Box<dyn Optimizer>,
+}
+
+impl NoisyOrModel {
+    pub fn new_mutable(
+        connection: &mut Connection,
+        namespace: String,
+        options: &CommandLineOptions,
+    ) -> Result<Box<dyn FactorModel>, Box<dyn Error>> {
+        let parameters = NoisyOrParameters::new(namespace.clone())?;
+        let mut frozen_activations = HashMap::new();
+        for (implication_key, value) in get_frozen_implications(connection, &namespace)? {
+            frozen_activations.insert(
+                activation_feature(&implication_key),
+                value.activation(&implication_key)?,
+            );
+        }
+        let optimizer = OptimizerFactory::new_mutable(&namespace, options)?;
+        Ok(Box::new(NoisyOrModel {
+            print_training_loss: options.print_training_loss,
+            parameters,
+            frozen_activations,
+            resume_training: options.resume_training || options.incremental,
+            optimizer,
+        }))
+    }
+
+    pub fn new_shared(namespace: String) -> Result<Arc<dyn FactorModel>, Box<dyn Error>> {
+        let parameters = NoisyOrParameters::new(namespace.clone())?;
+        Ok(Arc::new(NoisyOrModel {
+            print_training_loss: false,
+            parameters,
+            frozen_activations: HashMap::new(),
+            resume_training: false,
+            optimizer: Box::new(SgdOptimizer::new(DEFAULT_LEARNING_RATE)),
+        }))
+    }
+
+    fn read_activations(
+        &self,
+        connection: &mut Connection,
+        factor: &FactorContext,
+    ) -> Result<Vec<f64>, Box<dyn Error>> {
+        let mut activations = vec![];
+        for premise in &factor.factor {
+            let feature = activation_feature(&premise.inference.unique_key());
+            let activation = self
+                .parameters
+                .read_probability(connection, &feature)?
+                .ok_or(format!("No activation stored for {}", feature))?;
+            activations.push(activation);
+        }
+        Ok(activations)
+    }
+
+    fn read_leak(
+        &self,
+        connection: &mut Connection,
+        relation_name: &str,
+    ) -> Result<f64, Box<dyn Error>> {
+        if let Some(leak) = self
+            .parameters
+            .read_probability(connection, &leak_feature(relation_name))?
+        {
+            return Ok(leak);
+        }
+        let leak = self
+            .parameters
+            .read_probability(connection, LEGACY_LEAK_FEATURE)?
+            .unwrap_or(INITIAL_LEAK);
+        Ok(leak)
+    }
+}
+
+/// The probability that the conclusion stays false.
+fn compute_survival(leak: f64, activations: &[f64], probabilities: &[f64]) -> f64 {
+    let mut survival = 1f64 - leak;
+    for (activation, probability) in activations.iter().zip(probabilities) {
+        survival *= 1f64 - activation * probability;
+    }
+    survival
+}
+
+impl FactorModel for NoisyOrModel {
+    fn initialize_connection(
+        &mut self,
+        connection: &mut Connection,
+        implication: &ImplicationFactor,
+    ) -> Result<(), Box<dyn Error>> {
+        let feature = activation_feature(&implication.unique_key());
+        let mut rng = keyed_rng(&feature);
+        let activation = match self.frozen_activations.get(&feature) {
+            Some(activation) => *activation,
+            None => 0.5f64 + (rng.gen::<f64>() - 0.5f64) / 5.0,
+        };
+        trace!("initialize_connection - {} {}", feature, activation);
+        self.parameters
+            .save_probability(connection, &feature, activation)?;
+        self.optimizer.reset_features(connection, &[feature])?;
+        // The leak is shared by every implication into the same relation. When resuming, it is
+        // only initialized if no implication has been, so re-applying a frozen activation keeps
+        // the trained leak. Otherwise training starts over, and so does the leak.
+        let leak = leak_feature(&implication.conclusion.relation.relation_name);
+        let keep_leak = self.resume_training
+            && self
+                .parameters
+                .read_probability(connection, &leak)?
+                .is_some();
+        if !keep_leak {
+            self.parameters
+                .save_probability(connection, &leak, INITIAL_LEAK)?;
+            self.optimizer.reset_features(connection, &[leak])?;
+        }
+        Ok(())
+    }
+
+    fn is_connection_initialized(
+        &self,
+        connection: &mut Connection,
+        implication: &ImplicationFactor,
+    ) -> Result<bool, Box<dyn Error>> {
+        let feature = activation_feature(&implication.unique_key());
+        Ok(self
+            .parameters
+            .read_probability(connection, &feature)?
+            .is_some())
+    }
+
+    fn train(
+        &mut self,
+        connection: &mut Connection,
+        factor: &FactorContext,
+        gold_probability: f64,
+        example_weight: f64,
+    ) -> Result<TrainStatistics, Box<dyn Error>> {
+        let activations = self.read_activations(connection, factor)?;
+        let relation_name = conclusion_relation(factor)?;
+        let leak = self.read_leak(connection, &relation_name)?;
+        let survival = compute_survival(leak, &activations, &factor.probabilities)
+            .clamp(MIN_PROBABILITY, 1f64 - MIN_PROBABILITY);
+        let statistics = TrainStatistics::new(1f64 - survival, gold_probability);
+        // d log-likelihood / d survival, scaled by the weight of the example
+        let d_survival = example_weight
+            * (-gold_probability / (1f64 - survival) + (1f64 - gold_probability) / survival);
+        let mut logits = HashMap::new();
+        let mut gradient = HashMap::new();
+        for (i, premise) in factor.factor.iter().enumerate() {
+            let feature = activation_feature(&premise.inference.unique_key());
+            let q = activations[i];
+            let x = factor.probabilities[i];
+            if self.frozen_activations.contains_key(&feature) {
+                continue;
+            }
+            let d_q = d_survival * -x * survival / (1f64 - q * x).max(MIN_PROBABILITY);
+            logits.insert(feature.clone(), logit(q));
+            gradient.insert(feature, d_q * q * (1f64 - q));
+        }
+        let d_leak = d_survival * -survival / (1f64 - leak);
+        let leak_key = leak_feature(&relation_name);
+        logits.insert(leak_key.clone(), logit(leak));
+        gradient.insert(leak_key, d_leak * leak * (1f64 - leak));
+        let new_logits = self.optimizer.update(connection, &logits, &gradient)?;
+        for (feature, value) in &new_logits {
+            if self.print_training_loss {
+                trace!(
+                    "feature: {}, gradient: {}, old: {}, new: {}",
+                    feature,
+                    gradient[feature],
+                    sigmoid(logits[feature]),
+                    sigmoid(*value)
+                );
+            }
+            self.parameters
+                .save_probability(connection, feature, sigmoid(*value))?;
+        }
+        Ok(statistics)
+    }
+
+    fn predict(
+        &self,
+        connection: &mut Connection,
+        factor: &FactorContext,
+    ) -> Result<PredictStatistics, Box<dyn Error>> {
+        let activations = self.read_activations(connection, factor)?;
+        let leak = self.read_leak(connection, &conclusion_relation(factor)?)?;
+        let survival = compute_survival(leak, &activations, &factor.probabilities);
+        let probability = 1f64 - survival;
+        trace!("noisy_or predict: leak {}, activations {:?}, probability {}", leak, activations, probability);
+        Ok(PredictStatistics { probability })
+    }
+}
@@ -196,6 +196,22 @@ impl Predicate {
         format!("{}[{}]", &self.relation.relation_name, role_strings.join(","))
     }
 
+    /// The relation name with the domain of each role, e.g., "lonely[subject=man]".
+    pub fn domain_signature(&self) -> String {
+        let role_strings: Vec<String> = self
+            .roles
+            .iter()
+            .map(|role| {
+                let domain = match &role.argument {
+                    Argument::Constant(argument) => &argument.domain,
+                    Argument::Variable(argument) => &argument.domain,
+                };
+                format!("{}={}", role.role_name, domain)
+            })
+            .collect();
+        format!("{}[{}]", &self.relation.relation_name, role_strings.join(","))
+    }
+
     pub fn role_names(&self) -> Vec<String> {
         self.roles
             .iter()
@@ -0,0 +1,278 @@
+use crate::common::{
+    redis::{map_delete, map_get, map_insert},
+    setup::CommandLineOptions,
+};
+use redis::Connection;
+use std::{collections::HashMap, error::Error};
+
+/// Turns the log-likelihood gradient for a set of features into new weights.
+/// Any per-feature state lives in redis, next to the `weights` hash, so that training can be
+/// resumed by a later process.
+pub trait Optimizer {
+    fn update(
+        &mut self,
+        connection: &mut Connection,
+        weights: &HashMap<String, f64>,
+        gradient: &HashMap<String, f64>,
+    ) -> Result<HashMap<String, f64>, Box<dyn Error>>;
+
+    /// Forget the state for `features`, e.g., when their weights are re-initialized.
+    fn reset_features(
+        &mut self,
+        connection: &mut Connection,
+        features: &[String],
+    ) -> Result<(), Box<dyn Error>>;
+}
+
+/// The key of the hash of Adam's per-feature step counts.
+pub const ADAM_STEPS_KEY: &str = "optimizer:adam_steps";
+
+/// One hash of per-feature values, stored in the namespace under "optimizer:<slot>".
+struct OptimizerState {
+    namespace: String,
+    key: String,
+}
+
+impl OptimizerState {
+    fn new(namespace: &str, slot: &str) -> OptimizerState {
+        OptimizerState {
+            namespace: namespace.to_string(),
+            key: format!("optimizer:{}", slot),
+        }
+    }
+
+    fn read(&self, connection: &mut Connection, feature: &str) -> Result<f64, Box<dyn Error>> {
+        let record = map_get(connection, &self.namespace, &self.key, feature)?;
+        match record {
+            Some(record) => record
+                .parse::<f64>()
+                .map_err(|e| Box::new(e) as Box<dyn Error>),
+            None => Ok(0f64),
+        }
+    }
+
+    fn save(
+        &self,
+        connection: &mut Connection,
+        feature: &str,
+        value: f64,
+    ) -> Result<(), Box<dyn Error>> {
+        map_insert(
+            connection,
+            &self.namespace,
+            &self.key,
+            feature,
+            &value.to_string(),
+        )
+    }
+
+    fn clear(&self, connection: &mut Connection, features: &[String]) -> Result<(), Box<dyn Error>> {
+        for feature in features {
+            map_delete(connection, &self.namespace, &self.key, feature)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct SgdOptimizer {
+    learning_rate: f64,
+}
+
+impl SgdOptimizer {
+    pub fn new(learning_rate: f64) -> SgdOptimizer {
+        SgdOptimizer { learning_rate }
+    }
+}
+
+impl Optimizer for SgdOptimizer {
+    fn update(
+        &mut self,
+        _connection: &mut Connection,
+        weights: &HashMap<String, f64>,
+        gradient: &HashMap<String, f64>,
+    ) -> Result<HashMap<String, f64>, Box<dyn Error>> {
+        let mut new_weights = HashMap::new();
+        for (feature, &wv) in weights {
+            let g = gradient.get(feature).unwrap_or(&0.0);
+            new_weights.insert(feature.clone(), wv + self.learning_rate * g);
+        }
+        Ok(new_weights)
+    }
+
+    fn reset_features(
+        &mut self,
+        _connection: &mut Connection,
+        _features: &[String],
+    ) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+}
+
+pub struct MomentumOptimizer {
+    learning_rate: f64,
+    momentum: f64,
+    velocity: OptimizerState,
+}
+
+impl MomentumOptimizer {
+    pub fn new(namespace: &str, learning_rate: f64, momentum: f64) -> MomentumOptimizer {
+        MomentumOptimizer {
+            learning_rate,
+            momentum,
+            velocity: OptimizerState::new(namespace, "momentum_velocity"),
+        }
+    }
+}
+
+impl Optimizer for MomentumOptimizer {
+    fn update(
+        &mut self,
+        connection: &mut Connection,
+        weights: &HashMap<String, f64>,
+        gradient: &HashMap<String, f64>,
+    ) -> Result<HashMap<String, f64>, Box<dyn Error>> {
+        let mut new_weights = HashMap::new();
+        for (feature, &wv) in weights {
+            let g = gradient.get(feature).unwrap_or(&0.0);
+            let old_velocity = self.velocity.read(connection, feature)?;
+            let velocity = self.momentum * old_velocity + self.learning_rate * g;
+            self.velocity.save(connection, feature, velocity)?;
+            new_weights.insert(feature.clone(), wv + velocity);
+        }
+        Ok(new_weights)
+    }
+
+    fn reset_features(
+        &mut self,
+        connection: &mut Connection,
+        features: &[String],
+    ) -> Result<(), Box<dyn Error>> {
+        self.velocity.clear(connection, features)
+    }
+}
+
+pub struct AdaGradOptimizer {
+    learning_rate: f64,
+    epsilon: f64,
+    accumulator: OptimizerState,
+}
+
+impl AdaGradOptimizer {
+    pub fn new(namespace: &str, learning_rate: f64) -> AdaGradOptimizer {
+        AdaGradOptimizer {
+            learning_rate,
+            epsilon: 1e-8,
+            accumulator: OptimizerState::new(namespace, "adagrad_accumulator"),
+        }
+    }
+}
+
+impl Optimizer for AdaGradOptimizer {
+    fn update(
+        &mut self,
+        connection: &mut Connection,
+        weights: &HashMap<String, f64>,
+        gradient: &HashMap<String, f64>,
+    ) -> Result<HashMap<String, f64>, Box<dyn Error>> {
+        let mut new_weights = HashMap::new();
+        for (feature, &wv) in weights {
+            let g = gradient.get(feature).unwrap_or(&0.0);
+            let accumulated = self.accumulator.read(connection, feature)? + g * g;
+            self.accumulator.save(connection, feature, accumulated)?;
+            let step = self.learning_rate * g / (accumulated.sqrt() + self.epsilon);
+            new_weights.insert(feature.clone(), wv + step);
+        }
+        Ok(new_weights)
+    }
+
+    fn reset_features(
+        &mut self,
+        connection: &mut Connection,
+        features: &[String],
+    ) -> Result<(), Box<dyn Error>> {
+        self.accumulator.clear(connection, features)
+    }
+}
+
+pub struct AdamOptimizer {
+    learning_rate: f64,
+    beta1: f64,
+    beta2: f64,
+    epsilon: f64,
+    first_moment: OptimizerState,
+    second_moment: OptimizerState,
+    // Features are updated sparsely, so bias correction uses a step count per feature.
+    steps: OptimizerState,
+}
+
+impl AdamOptimizer {
+    pub fn new(namespace: &str, learning_rate: f64) -> AdamOptimizer {
+        AdamOptimizer {
+            learning_rate,
+            beta1: 0.9,
+            beta2: 0.999,
+            epsilon: 1e-8,
+            first_moment: OptimizerState::new(namespace, "adam_first_moment"),
+            second_moment: OptimizerState::new(namespace, "adam_second_moment"),
+            steps: OptimizerState {
+                namespace: namespace.to_string(),
+                key: ADAM_STEPS_KEY.to_string(),
+            },
+        }
+    }
+}
+
+impl Optimizer for AdamOptimizer {
+    fn update(
+        &mut self,
+        connection: &mut Connection,
+        weights: &HashMap<String, f64>,
+        gradient: &HashMap<String, f64>,
+    ) -> Result<HashMap<String, f64>, Box<dyn Error>> {
+        let mut new_weights = HashMap::new();
+        for (feature, &wv) in weights {
+            let g = gradient.get(feature).unwrap_or(&0.0);
+            let step = self.steps.read(connection, feature)? + 1f64;
+            let m = self.beta1 * self.first_moment.read(connection, feature)?
+                + (1f64 - self.beta1) * g;
+            let v = self.beta2 * self.second_moment.read(connection, feature)?
+                + (1f64 - self.beta2) * g * g;
+            self.steps.save(connection, feature, step)?;
+            self.first_moment.save(connection, feature, m)?;
+            self.second_moment.save(connection, feature, v)?;
+            let m_hat = m / (1f64 - self.beta1.powf(step));
+            let v_hat = v / (1f64 - self.beta2.powf(step));
+            let new_weight = wv + self.learning_rate * m_hat / (v_hat.sqrt() + self.epsilon);
+            new_weights.insert(feature.clone(), new_weight);
+        }
+        Ok(new_weights)
+    }
+
+    fn reset_features(
+        &mut self,
+        connection: &mut Connection,
+        features: &[String],
+    ) -> Result<(), Box<dyn Error>> {
+        self.first_moment.clear(connection, features)?;
+        self.second_moment.clear(connection, features)?;
+        self.steps.clear(connection, features)
+    }
+}
+
+pub struct OptimizerFactory;
+
+impl OptimizerFactory {
+    pub fn new_mutable(
+        namespace: &str,
+        options: &CommandLineOptions,
+    ) -> Result<Box<dyn Optimizer>, Box<dyn Error>> {
+        let learning_rate = options.learning_rate;
+        match options.optimizer.as_str() {
+            "sgd" => Ok(Box::new(SgdOptimizer::new(learning_rate))),
+            "momentum" => Ok(Box::new(MomentumOptimizer::new(namespace, learning_rate, 0.9))),
+            "adagrad" => Ok(Box::new(AdaGradOptimizer::new(namespace, learning_rate))),
+            "adam" => Ok(Box::new(AdamOptimizer::new(namespace, learning_rate))),
+            name => Err(format!("Unknown Optimizer type {}", name).into()),
+        }
+    }
+}
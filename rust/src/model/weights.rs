@@ -1,9 +1,10 @@
 use crate::{
     common::{
+        random::keyed_rng,
         redis::{map_get, map_insert},
         resources::ResourceContext,
     },
-    model::objects::ImplicationFactor,
+    model::objects::{ImplicationFactor, Predicate},
 };
 use rand::Rng;
 use redis::{Commands, Connection};
@@ -15,8 +16,8 @@ use std::{
 
 pub const CLASS_LABELS: [usize; 2] = [0, 1];
 
-fn random_weight() -> f64 {
-    let mut rng = rand::thread_rng();
+fn random_weight(feature: &str) -> f64 {
+    let mut rng = keyed_rng(feature);
     (rng.gen::<f64>() - rng.gen::<f64>()) / 5.0
 }
 
@@ -36,13 +37,92 @@ pub fn negative_feature(feature: &str, class_label: usize) -> String {
     format!("->{} {}", sign_char(class_label), feature)
 }
 
+/// A feature on the conjunction of several backimplications, see `model::interaction`.
+pub fn interaction_feature(interaction_key: &str, class_label: usize) -> String {
+    format!("*>{} {}", sign_char(class_label), interaction_key)
+}
+
+pub fn is_interaction_feature(feature: &str) -> bool {
+    feature.starts_with("*>")
+}
+
+/// A bias for the conclusion relation, independent of the premises.
+pub fn bias_feature(bias_key: &str, class_label: usize) -> String {
+    format!("b>{} {}", sign_char(class_label), bias_key)
+}
+
+pub fn is_bias_feature(feature: &str) -> bool {
+    feature.starts_with("b>")
+}
+
+/// The relation name of `conclusion`, and its domain signature if `domain_bias` is set.
+pub fn bias_keys(conclusion: &Predicate, domain_bias: bool) -> Vec<String> {
+    let mut result = vec![conclusion.relation.relation_name.clone()];
+    if domain_bias {
+        result.push(conclusion.domain_signature());
+    }
+    result
+}
+
+/// The bias features of `conclusion`, for every bias key and class label.
+pub fn bias_features(conclusion: &Predicate, domain_bias: bool) -> Vec<String> {
+    let mut result = vec![];
+    for bias_key in bias_keys(conclusion, domain_bias) {
+        for class_label in CLASS_LABELS {
+            result.push(bias_feature(&bias_key, class_label));
+        }
+    }
+    result
+}
+
+pub fn implication_features(implication: &ImplicationFactor) -> Vec<String> {
+    let feature = implication.unique_key();
+    let mut result = vec![];
+    for class_label in CLASS_LABELS {
+        result.push(positive_feature(&feature, class_label));
+        result.push(negative_feature(&feature, class_label));
+    }
+    result
+}
+
 pub struct ExponentialWeights {
     namespace: String,
+    /// The namespace that new weights are copied from when it has the same feature, if any.
+    warm_start: Option<String>,
 }
 
 impl ExponentialWeights {
     pub fn new(namespace: String) -> Result<ExponentialWeights, Box<dyn Error>> {
-        Ok(ExponentialWeights { namespace })
+        Ok(ExponentialWeights {
+            namespace,
+            warm_start: None,
+        })
+    }
+
+    /// Weights that are initialized from `warm_start` where it has the feature, and randomly
+    /// otherwise, e.g., for implications that were added since.
+    pub fn new_with_warm_start(
+        namespace: String,
+        warm_start: Option<String>,
+    ) -> Result<ExponentialWeights, Box<dyn Error>> {
+        Ok(ExponentialWeights {
+            namespace,
+            warm_start,
+        })
+    }
+
+    fn initial_weight(
+        &self,
+        connection: &mut Connection,
+        feature: &str,
+    ) -> Result<f64, Box<dyn Error>> {
+        if let Some(source) = &self.warm_start {
+            if let Some(record) = map_get(connection, source, Self::WEIGHTS_KEY, feature)? {
+                trace!("initial_weight - Warm start {} from {}", feature, source);
+                return Ok(record.parse::<f64>()?);
+            }
+        }
+        Ok(random_weight(feature))
     }
 }
 
@@ -53,6 +133,8 @@ impl ExponentialWeights {
         &mut self,
         connection: &mut Connection,
         implication: &ImplicationFactor,
+        domain_bias: bool,
+        reset_bias: bool,
     ) -> Result<(), Box<dyn Error>> {
         trace!("initialize_weights - Start: {:?}", implication);
         let feature = implication.unique_key();
@@ -65,8 +147,8 @@ impl ExponentialWeights {
                 posf,
                 negf
             );
-            let weight1 = random_weight();
-            let weight2 = random_weight();
+            let weight1 = self.initial_weight(connection, &posf)?;
+            let weight2 = self.initial_weight(connection, &negf)?;
             trace!(
                 "initialize_weights - Generated weights: {}, {}",
                 weight1,
@@ -87,10 +169,39 @@ impl ExponentialWeights {
                 &weight2.to_string(),
             )?;
         }
+        // The bias is shared by every implication into the same relation, so unless `reset_bias`
+        // is set, it is only initialized by the first.
+        for biasf in bias_features(&implication.conclusion, domain_bias) {
+            if reset_bias
+                || map_get(connection, &self.namespace, Self::WEIGHTS_KEY, &biasf)?.is_none()
+            {
+                let weight = self.initial_weight(connection, &biasf)?;
+                map_insert(
+                    connection,
+                    &self.namespace,
+                    Self::WEIGHTS_KEY,
+                    &biasf,
+                    &weight.to_string(),
+                )?;
+            }
+        }
         trace!("initialize_weights - End");
         Ok(())
     }
 
+    pub fn has_weights(
+        &self,
+        connection: &mut Connection,
+        implication: &ImplicationFactor,
+    ) -> Result<bool, Box<dyn Error>> {
+        for feature in implication_features(implication) {
+            if map_get(connection, &self.namespace, Self::WEIGHTS_KEY, &feature)?.is_none() {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
     pub fn read_single_weight(
         &self,
         connection: &mut Connection,
@@ -117,6 +228,12 @@ impl ExponentialWeights {
         trace!("read_weights - Start");
         let mut weights = HashMap::new();
         for feature in features {
+            // Interaction weights are created lazily, the first time the interaction is seen, and
+            // bias weights may be missing in namespaces trained before they were added.
+            if is_interaction_feature(feature) || is_bias_feature(feature) {
+                weights.insert(feature.clone(), self.read_single_weight(connection, feature)?);
+                continue;
+            }
             trace!("read_weights - Reading weight for feature: {}", feature);
             let weight_record = map_get(connection, &self.namespace, Self::WEIGHTS_KEY, &feature)?
                 .expect("should be there");
@@ -154,3 +271,17 @@ impl ExponentialWeights {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::random::{set_global_seed, tests::TEST_SEED};
+
+    #[test]
+    fn seeded_random_weight_is_stable() {
+        set_global_seed(TEST_SEED);
+        let first = random_weight("+>+ feature");
+        assert_eq!(first, random_weight("+>+ feature"));
+        assert!(first.abs() <= 0.2);
+    }
+}
@@ -2,6 +2,7 @@ use crate::common::graph::InferenceGraph;
 use crate::common::interface::BeliefTable;
 use crate::common::model::InferenceModel;
 use crate::common::proposition_db::RedisBeliefTable;
+use crate::common::random::random_f64;
 use crate::common::redis::RedisManager;
 use crate::common::resources::{self, ResourceContext};
 use crate::common::train::TrainingPlan;
@@ -13,11 +14,9 @@ use crate::{
         objects::{Domain, Entity, RoleMap},
     },
 };
-use rand::Rng; // Import Rng trait
 use std::{collections::HashMap, error::Error};
 fn cointoss() -> f64 {
-    let mut rng = rand::thread_rng(); // Get a random number generator
-    if rng.gen::<f64>() < 0.5 {
+    if random_f64() < 0.5 {
         1.0
     } else {
         0.0
@@ -25,8 +24,7 @@ fn cointoss() -> f64 {
 }
 
 fn weighted_cointoss(threshold: f64) -> f64 {
-    let mut rng = rand::thread_rng(); // Get a random number generator
-    if rng.gen::<f64>() < threshold {
+    if random_f64() < threshold {
         1.0
     } else {
         0.0
@@ -40,7 +38,7 @@ impl ScenarioMaker for SimpleDating {
         let mut connection = resources.connection.lock().unwrap();
         let namespace = "dating_simple".to_string();
         let mut graph = InferenceGraph::new_mutable(namespace.clone())?;
-        let proposition_db = RedisBeliefTable::new_mutable(namespace.clone())?;
+        let proposition_db = RedisBeliefTable::new_mutable(&mut connection, namespace.clone())?;
         let mut plan = TrainingPlan::new(namespace.clone())?;
         let total_members_each_class = 1024;
         let entity_domains = [Domain::MAN.to_string(), Domain::WOMAN.to_string()];
@@ -228,13 +226,12 @@ impl ScenarioMaker for SimpleDating {
                     p_jack_dates_jill
                 ); // Logging
 
-                if is_training {
-                    proposition_db.store_proposition_probability(
-                        &mut connection,
-                        &jack_dates_jill,
-                        p_jack_dates_jill,
-                    )?;
-                }
+                // Stored for test entities too, as the gold value for evaluation.
+                proposition_db.store_proposition_probability(
+                    &mut connection,
+                    &jack_dates_jill,
+                    p_jack_dates_jill,
+                )?;
                 plan.maybe_add_to_training(&mut connection, is_training, &jack_dates_jill)?;
                 plan.maybe_add_to_test(&mut connection, is_test, &jack_dates_jill)?;
                 // graph.ensure_existence_backlinks_for_proposition(&jack_dates_jill)?;
@@ -1,8 +1,7 @@
 
-use rand::Rng;
+use crate::common::random::random_f64;
 pub fn weighted_cointoss(threshold: f64) -> bool {
-    let mut rng = rand::thread_rng(); // Get a random number generator
-    if rng.gen::<f64>() < threshold {
+    if random_f64() < threshold {
         true
     } else {
         false
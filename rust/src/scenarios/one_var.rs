@@ -2,6 +2,7 @@ use crate::common::graph::InferenceGraph;
 use crate::common::interface::BeliefTable;
 use crate::common::model::InferenceModel;
 use crate::common::proposition_db::RedisBeliefTable;
+use crate::common::random::random_f64;
 use crate::common::redis::RedisManager;
 use crate::common::resources::{self, ResourceContext};
 use crate::common::train::TrainingPlan;
@@ -15,11 +16,9 @@ use crate::{
     },
 };
 use crate::{print_red, print_yellow};
-use rand::Rng; // Import Rng trait
 use std::{collections::HashMap, error::Error};
 fn cointoss() -> f64 {
-    let mut rng = rand::thread_rng(); // Get a random number generator
-    if rng.gen::<f64>() < 0.5 {
+    if random_f64() < 0.5 {
         1.0
     } else {
         0.0
@@ -27,8 +26,7 @@ fn cointoss() -> f64 {
 }
 
 fn weighted_cointoss(threshold: f64) -> f64 {
-    let mut rng = rand::thread_rng(); // Get a random number generator
-    if rng.gen::<f64>() < threshold {
+    if random_f64() < threshold {
         1.0
     } else {
         0.0